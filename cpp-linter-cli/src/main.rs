@@ -1,7 +1,8 @@
 use std::env;
+use std::process::exit;
 
 use cpp_linter_lib::run::run_main;
 
 pub fn main() {
-    run_main(env::args().collect::<Vec<String>>());
+    exit(run_main(env::args().collect::<Vec<String>>()));
 }
@@ -16,12 +16,18 @@ pub fn main() -> std::io::Result<()> {
     }
     write!(&doc_file, "\n\n")?;
     for arg in command.get_arguments() {
-        writeln!(
-            &doc_file,
-            ".. std:option:: -{}, --{}\n",
-            &arg.get_short().unwrap(),
-            &arg.get_long().unwrap()
-        )?;
+        let mut names = Vec::new();
+        if let Some(short) = arg.get_short() {
+            names.push(format!("-{short}"));
+        }
+        if let Some(long) = arg.get_long() {
+            names.push(format!("--{long}"));
+        }
+        if names.is_empty() {
+            // a positional argument
+            names.push(arg.get_id().to_string());
+        }
+        writeln!(&doc_file, ".. std:option:: {}\n", names.join(", "))?;
         for line in arg.get_long_help().unwrap().to_string().split('\n') {
             writeln!(&doc_file, "    {}", &line)?;
         }
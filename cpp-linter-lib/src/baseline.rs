@@ -0,0 +1,366 @@
+//! This module holds functionality related to the optional baseline file.
+//!
+//! A baseline is a snapshot of the concerns found in a previous run. Concerns that are
+//! recorded in the baseline are suppressed, so that only new concerns are reported (and
+//! counted as `checks-failed`).
+//!
+//! Each concern is identified by its file, its diagnostic name (`clang-format` for
+//! formatting concerns), and a fingerprint of the concerned line's content. Line numbers
+//! are not recorded, so a concern still matches the baseline if its line is moved (eg.
+//! by adding lines above it). If the baseline records the same concern `N` times, then
+//! at most `N` matching concerns are suppressed.
+
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::Path;
+
+// non-std crates
+use serde::{Deserialize, Serialize};
+
+// project specific modules/crates
+use crate::clang_tools::clang_format::{make_patch, FormatAdvice};
+use crate::clang_tools::clang_tidy::TidyNotification;
+use crate::common_fs::FileObj;
+
+/// The diagnostic name used for formatting concerns in a [`Baseline`].
+const FORMAT_DIAGNOSTIC: &str = "clang-format";
+
+/// A single concern recorded in a [`Baseline`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct BaselineFinding {
+    /// The file's path and name (relative to the repository root).
+    pub file: String,
+
+    /// The diagnostic name (or `clang-format` for formatting concerns).
+    pub diagnostic: String,
+
+    /// The fingerprint of the concerned line's content (see [`fingerprint()`]).
+    pub fingerprint: String,
+}
+
+/// A structure to represent the (de)serialized baseline file.
+#[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Baseline {
+    /// The recorded concerns.
+    pub findings: Vec<BaselineFinding>,
+}
+
+/// Create a fingerprint of a `line`'s content.
+///
+/// This is the 64-bit FNV-1a hash (as a hexadecimal string) of the `line` without
+/// leading or trailing whitespace, so changes in indentation do not change the
+/// fingerprint.
+pub fn fingerprint(line: &str) -> String {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for byte in line.trim().bytes() {
+        hash ^= byte as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    format!("{hash:016x}")
+}
+
+/// A cache of files' lines, so that each file is only read once.
+#[derive(Default)]
+struct LineReader {
+    files: HashMap<String, Vec<String>>,
+}
+
+impl LineReader {
+    /// Get the fingerprint of the given `line` (1-based) in the given `file`.
+    ///
+    /// Returns [`None`] if the file could not be read or the `line` does not exist.
+    fn fingerprint(&mut self, file: &str, line: usize) -> Option<String> {
+        let lines = self.files.entry(file.to_string()).or_insert_with(|| {
+            fs::read(file)
+                .map(|content| {
+                    String::from_utf8_lossy(&content)
+                        .lines()
+                        .map(|line| line.to_string())
+                        .collect()
+                })
+                .unwrap_or_default()
+        });
+        lines
+            .get(line.checked_sub(1)?)
+            .map(|content| fingerprint(content))
+    }
+}
+
+/// Normalize the given `file_name` as recorded in a [`Baseline`].
+fn normalize_name(file_name: &Path) -> String {
+    let name = file_name.to_string_lossy().replace('\\', "/");
+    name.strip_prefix("./").unwrap_or(&name).to_string()
+}
+
+impl Baseline {
+    /// Create a baseline from the concerns in `format_advice` and `tidy_advice` about
+    /// the given set of `files`.
+    ///
+    /// A formatting concern is recorded for each line that clang-format wants to change.
+    /// Clang-tidy concerns about files that are not in `files` (eg. system headers) are
+    /// not recorded.
+    pub fn from_findings(
+        files: &[FileObj],
+        format_advice: &[FormatAdvice],
+        tidy_advice: &[Vec<TidyNotification>],
+    ) -> Self {
+        let mut reader = LineReader::default();
+        let mut findings = Vec::new();
+        let reviewed = files
+            .iter()
+            .map(|file| normalize_name(&file.name))
+            .collect::<HashSet<_>>();
+        for (index, advice) in format_advice.iter().enumerate() {
+            let file = normalize_name(&files[index].name);
+            let mut lines = advice
+                .replacements
+                .iter()
+                .filter_map(|replacement| replacement.line)
+                .collect::<Vec<_>>();
+            lines.sort_unstable();
+            lines.dedup();
+            for line in lines {
+                if let Some(fingerprint) = reader.fingerprint(&file, line) {
+                    findings.push(BaselineFinding {
+                        file: file.clone(),
+                        diagnostic: FORMAT_DIAGNOSTIC.to_string(),
+                        fingerprint,
+                    });
+                }
+            }
+        }
+        for notes in tidy_advice {
+            for note in notes {
+                let file = normalize_name(Path::new(&note.filename));
+                if !reviewed.contains(&file) {
+                    continue;
+                }
+                if let Some(fingerprint) = reader.fingerprint(&file, note.line as usize) {
+                    findings.push(BaselineFinding {
+                        file,
+                        diagnostic: note.diagnostic.clone(),
+                        fingerprint,
+                    });
+                }
+            }
+        }
+        Baseline { findings }
+    }
+
+    /// Read the baseline file at the given `path`.
+    pub fn from_file(path: &Path) -> Result<Self, String> {
+        let content = fs::read_to_string(path).map_err(|e| e.to_string())?;
+        serde_json::from_str(&content).map_err(|e| e.to_string())
+    }
+
+    /// Write this baseline to a file at the given `path`.
+    pub fn to_file(&self, path: &Path) -> Result<(), String> {
+        let content = serde_json::to_string_pretty(self).map_err(|e| e.to_string())?;
+        fs::write(path, content + "\n").map_err(|e| e.to_string())
+    }
+
+    /// Remove the concerns recorded in this baseline from the given `format_advice` and
+    /// `tidy_advice` about the given set of `files`.
+    ///
+    /// The [`FormatAdvice::patch`] of any file with suppressed formatting concerns is
+    /// updated to only show the remaining changes.
+    ///
+    /// Returns the number of suppressed concerns.
+    pub fn filter(
+        &self,
+        files: &[FileObj],
+        format_advice: &mut [FormatAdvice],
+        tidy_advice: &mut [Vec<TidyNotification>],
+    ) -> usize {
+        let mut remaining: HashMap<&BaselineFinding, usize> = HashMap::new();
+        for finding in &self.findings {
+            *remaining.entry(finding).or_default() += 1;
+        }
+        let mut reader = LineReader::default();
+        let mut is_suppressed = |file: &str, diagnostic: &str, line: usize| {
+            let Some(fingerprint) = reader.fingerprint(file, line) else {
+                return false;
+            };
+            let finding = BaselineFinding {
+                file: file.to_string(),
+                diagnostic: diagnostic.to_string(),
+                fingerprint,
+            };
+            match remaining.get_mut(&finding) {
+                Some(count) if *count > 0 => {
+                    *count -= 1;
+                    true
+                }
+                _ => false,
+            }
+        };
+
+        let mut suppressed = 0;
+        for (index, advice) in format_advice.iter_mut().enumerate() {
+            if advice.replacements.is_empty() {
+                continue;
+            }
+            let file = normalize_name(&files[index].name);
+            // a formatting concern is about a line (which may have many replacements)
+            let mut line_suppressed: HashMap<usize, bool> = HashMap::new();
+            let before = advice.replacements.len();
+            advice.replacements.retain(|replacement| {
+                let Some(line) = replacement.line else {
+                    return true;
+                };
+                let suppressed = *line_suppressed
+                    .entry(line)
+                    .or_insert_with(|| is_suppressed(&file, FORMAT_DIAGNOSTIC, line));
+                !suppressed
+            });
+            suppressed += line_suppressed.values().filter(|val| **val).count();
+            if advice.replacements.len() != before {
+                advice.patch = if advice.replacements.is_empty() {
+                    None
+                } else {
+                    fs::read(&files[index].name).ok().and_then(|content| {
+                        make_patch(&files[index].name, &content, &advice.replacements)
+                    })
+                };
+            }
+        }
+        for notes in tidy_advice.iter_mut() {
+            notes.retain(|note| {
+                let file = normalize_name(Path::new(&note.filename));
+                let matched = is_suppressed(&file, &note.diagnostic, note.line as usize);
+                if matched {
+                    suppressed += 1;
+                }
+                !matched
+            });
+        }
+        suppressed
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs;
+    use std::path::{Path, PathBuf};
+
+    use super::{fingerprint, Baseline};
+    use crate::clang_tools::clang_format::{FormatAdvice, Replacement};
+    use crate::clang_tools::clang_tidy::TidyNotification;
+    use crate::common_fs::FileObj;
+
+    fn make_note(file: &Path, line: u32, diagnostic: &str) -> TidyNotification {
+        TidyNotification {
+            filename: file.to_string_lossy().to_string(),
+            line,
+            cols: 1,
+            severity: String::from("warning"),
+            rationale: String::from("rationale"),
+            diagnostic: diagnostic.to_string(),
+            suggestion: vec![],
+            related: vec![],
+        }
+    }
+
+    fn make_format_advice(lines: &[usize]) -> FormatAdvice {
+        FormatAdvice {
+            replacements: lines
+                .iter()
+                .map(|line| Replacement {
+                    offset: 0,
+                    length: 0,
+                    value: None,
+                    line: Some(*line),
+                    cols: Some(1),
+                })
+                .collect(),
+            patch: None,
+        }
+    }
+
+    #[test]
+    fn fingerprints() {
+        assert_eq!(fingerprint(""), "cbf29ce484222325");
+        assert_eq!(fingerprint("a"), "af63dc4c8601ec8c");
+        assert_eq!(fingerprint("  int a;\t"), fingerprint("int a;"));
+        assert_ne!(fingerprint("int a;"), fingerprint("int b;"));
+    }
+
+    #[test]
+    fn filter_shifted_lines() {
+        let tmp = tempfile::tempdir().unwrap();
+        let src = tmp.path().join("demo.cpp");
+        fs::write(&src, "int a;\nint b;\nint b;\n").unwrap();
+        let files = vec![FileObj::new(src.clone())];
+        let tidy_advice = vec![vec![
+            make_note(&src, 1, "misc-a"),
+            make_note(&src, 2, "misc-b"),
+        ]];
+        let format_advice = vec![make_format_advice(&[1, 1, 2])];
+        let baseline = Baseline::from_findings(&files, &format_advice, &tidy_advice);
+        assert_eq!(baseline.findings.len(), 4);
+        let baseline_path = tmp.path().join("baseline.json");
+        baseline.to_file(&baseline_path).unwrap();
+        let baseline = Baseline::from_file(&baseline_path).unwrap();
+
+        // lines moved down (and re-indented) and a new concern was added
+        fs::write(&src, "// new line\n  int a;\nint b;\nint b;\nint c;\n").unwrap();
+        let mut tidy_advice = vec![vec![
+            make_note(&src, 2, "misc-a"),
+            make_note(&src, 3, "misc-b"),
+            // the same concern about an identical line is only suppressed once
+            make_note(&src, 4, "misc-b"),
+            make_note(&src, 5, "misc-c"),
+        ]];
+        let mut format_advice = vec![make_format_advice(&[2, 2, 4, 5])];
+        let suppressed = baseline.filter(&files, &mut format_advice, &mut tidy_advice);
+        assert_eq!(suppressed, 4);
+        assert_eq!(
+            tidy_advice[0]
+                .iter()
+                .map(|note| (note.line, note.diagnostic.as_str()))
+                .collect::<Vec<_>>(),
+            vec![(4, "misc-b"), (5, "misc-c")]
+        );
+        // lines 2 and 4 were recorded (as "int a;" and "int b;")
+        assert_eq!(
+            format_advice[0]
+                .replacements
+                .iter()
+                .map(|r| r.line.unwrap())
+                .collect::<Vec<_>>(),
+            vec![5]
+        );
+    }
+
+    #[test]
+    fn record_reviewed_files() {
+        let tmp = tempfile::tempdir().unwrap();
+        let src = tmp.path().join("demo.cpp");
+        let header = tmp.path().join("system.h");
+        fs::write(&src, "int a;\nint b;\n").unwrap();
+        fs::write(&header, "int c;\n").unwrap();
+        let files = vec![FileObj::new(src.clone())];
+        let tidy_advice = vec![vec![
+            make_note(&src, 1, "misc-a"),
+            make_note(&header, 1, "misc-a"),
+        ]];
+        // replacements are not necessarily ordered by line
+        let format_advice = vec![make_format_advice(&[2, 1, 2])];
+        let baseline = Baseline::from_findings(&files, &format_advice, &tidy_advice);
+        let recorded = baseline
+            .findings
+            .iter()
+            .map(|finding| finding.diagnostic.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(recorded, vec!["clang-format", "clang-format", "misc-a"]);
+        assert!(baseline
+            .findings
+            .iter()
+            .all(|finding| finding.file.ends_with("demo.cpp")));
+    }
+
+    #[test]
+    fn missing_baseline() {
+        assert!(Baseline::from_file(&PathBuf::from("not-a-baseline.json")).is_err());
+    }
+}
@@ -1,22 +1,34 @@
 //! This module holds functionality specific to running clang-format and parsing it's
 //! output.
 
+use std::fs;
+use std::path::Path;
 use std::process::Command;
 
 // non-std crates
+use git2::{DiffOptions, Patch};
 use serde::Deserialize;
 use serde_xml_rs::de::Deserializer;
 
 // project-specific crates/modules
+use super::ToolError;
 use crate::common_fs::{get_line_cols_from_offset, FileObj};
 
 /// A Structure used to deserialize clang-format's XML output.
-#[derive(Debug, Deserialize, PartialEq)]
+#[derive(Debug, Default, Deserialize, PartialEq)]
 #[serde(rename = "replacements")]
 pub struct FormatAdvice {
     /// A list of [`Replacement`]s that clang-tidy wants to make.
-    #[serde(rename = "$value")]
+    #[serde(rename = "$value", default)]
     pub replacements: Vec<Replacement>,
+
+    /// A unified diff (without context lines) that shows the changes described by the
+    /// [`FormatAdvice::replacements`] (see [`make_patch()`]).
+    ///
+    /// This value is not provided by the XML output, but we calculate it after
+    /// deserialization.
+    #[serde(skip)]
+    pub patch: Option<String>,
 }
 
 /// A single replacement that clang-format wants to make.
@@ -58,12 +70,15 @@ impl Clone for Replacement {
 }
 
 /// Run clang-tidy for a specific `file`, then parse and return it's XML output.
+///
+/// Also returns a [`ToolError`] if clang-format exited with a non-zero status or its
+/// output could not be parsed.
 pub fn run_clang_format(
     cmd: &mut Command,
     file: &FileObj,
     style: &str,
     lines_changed_only: u8,
-) -> FormatAdvice {
+) -> (FormatAdvice, Option<ToolError>) {
     cmd.args(["--style", style, "--output-replacements-xml"]);
     let ranges = file.get_ranges(lines_changed_only);
     for range in &ranges {
@@ -79,10 +94,15 @@ pub fn run_clang_format(
             .join(" ")
     );
     let output = cmd.output().unwrap();
-    if !output.stderr.is_empty() || !output.status.success() {
-        log::debug!(
-            "clang-format raised the follow errors:\n{}",
-            String::from_utf8(output.stderr).unwrap()
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    if !stderr.is_empty() || !output.status.success() {
+        log::debug!("clang-format raised the follow errors:\n{stderr}");
+    }
+    if !output.status.success() {
+        let reason = "clang-format exited with a non-zero status.";
+        return (
+            FormatAdvice::default(),
+            Some(ToolError::new("clang-format", file, reason, &stderr)),
         );
     }
     // log::debug!(
@@ -90,9 +110,7 @@ pub fn run_clang_format(
     //     String::from_utf8(output.stdout.clone()).unwrap()
     // );
     if output.stdout.is_empty() {
-        return FormatAdvice {
-            replacements: vec![],
-        };
+        return (FormatAdvice::default(), None);
     }
     let xml = String::from_utf8(output.stdout)
         .unwrap()
@@ -104,10 +122,16 @@ pub fn run_clang_format(
         .whitespace_to_characters(true)
         .ignore_root_level_whitespace(true);
     let event_reader = serde_xml_rs::EventReader::new_with_config(xml.as_bytes(), config);
-    let mut format_advice: FormatAdvice =
-        FormatAdvice::deserialize(&mut Deserializer::new(event_reader)).unwrap_or(FormatAdvice {
-            replacements: vec![],
-        });
+    let mut format_advice = match FormatAdvice::deserialize(&mut Deserializer::new(event_reader)) {
+        Ok(advice) => advice,
+        Err(e) => {
+            let reason = format!("Could not parse the output of clang-format: {e}");
+            return (
+                FormatAdvice::default(),
+                Some(ToolError::new("clang-format", file, &reason, "")),
+            );
+        }
+    };
     if !format_advice.replacements.is_empty() {
         let mut filtered_replacements = Vec::new();
         for replacement in &mut format_advice.replacements {
@@ -127,14 +151,78 @@ pub fn run_clang_format(
         }
         format_advice.replacements = filtered_replacements;
     }
-    format_advice
+    if !format_advice.replacements.is_empty() {
+        format_advice.patch = fs::read(&file.name)
+            .ok()
+            .and_then(|content| make_patch(&file.name, &content, &format_advice.replacements));
+    }
+    (format_advice, None)
+}
+
+/// Make a unified diff (without context lines) that shows the changes described by
+/// the given `replacements` to the `original` content of a file.
+///
+/// Only the diff's hunks are returned (the diff's header is omitted). Returns [`None`]
+/// if the `replacements` do not change the `original` content.
+pub fn make_patch(
+    file_name: &Path,
+    original: &[u8],
+    replacements: &[Replacement],
+) -> Option<String> {
+    let mut sorted = replacements.iter().collect::<Vec<_>>();
+    sorted.sort_by_key(|replacement| replacement.offset);
+    let mut formatted = Vec::with_capacity(original.len());
+    let mut last = 0;
+    for replacement in sorted {
+        // skip overlapping (or invalid) replacements
+        if replacement.offset < last || replacement.offset + replacement.length > original.len() {
+            continue;
+        }
+        formatted.extend_from_slice(&original[last..replacement.offset]);
+        if let Some(value) = &replacement.value {
+            formatted.extend_from_slice(value.as_bytes());
+        }
+        last = replacement.offset + replacement.length;
+    }
+    formatted.extend_from_slice(&original[last..]);
+
+    let mut diff_opts = DiffOptions::new();
+    diff_opts.context_lines(0);
+    let mut patch = Patch::from_buffers(
+        original,
+        Some(file_name),
+        &formatted,
+        Some(file_name),
+        Some(&mut diff_opts),
+    )
+    .ok()?;
+    let buf = patch.to_buf().ok()?;
+    let diff = String::from_utf8_lossy(&buf);
+    let hunks = diff.find("@@").map(|start| &diff[start..])?;
+    Some(hunks.trim_end().to_string())
 }
 
 #[cfg(test)]
 mod tests {
-    use super::{FormatAdvice, Replacement};
+    use std::path::PathBuf;
+
+    use super::{make_patch, FormatAdvice, Replacement};
     use serde::Deserialize;
 
+    #[test]
+    fn parse_xml_without_replacements() {
+        let xml = "<?xml version='1.0'?><replacements xml:space='preserve' incomplete_format='false'></replacements>";
+        let config = serde_xml_rs::ParserConfig::new()
+            .trim_whitespace(false)
+            .whitespace_to_characters(true)
+            .ignore_root_level_whitespace(true);
+        let event_reader = serde_xml_rs::EventReader::new_with_config(xml.as_bytes(), config);
+        let document =
+            FormatAdvice::deserialize(&mut serde_xml_rs::de::Deserializer::new(event_reader))
+                .unwrap();
+        assert!(document.replacements.is_empty());
+    }
+
     #[test]
     fn parse_xml() {
         let xml_raw = r#"<?xml version='1.0'?>
@@ -178,6 +266,7 @@ mod tests {
                     cols: None,
                 },
             ],
+            patch: None,
         };
         let config = serde_xml_rs::ParserConfig::new()
             .trim_whitespace(false)
@@ -189,4 +278,49 @@ mod tests {
                 .unwrap();
         assert_eq!(expected, document);
     }
+
+    fn replacement(offset: usize, length: usize, value: &str) -> Replacement {
+        Replacement {
+            offset,
+            length,
+            value: Some(value.to_string()),
+            line: None,
+            cols: None,
+        }
+    }
+
+    #[test]
+    fn patch_from_replacements() {
+        let original = "int main(){\n  return 0;\n}\n\nint a;\nint  b;\n";
+        let replacements = vec![
+            // replacements are not necessarily ordered
+            replacement(37, 2, " "),
+            replacement(10, 0, " "),
+            replacement(12, 2, "    "),
+        ];
+        let patch = make_patch(
+            &PathBuf::from("src/demo.cpp"),
+            original.as_bytes(),
+            &replacements,
+        )
+        .unwrap();
+        assert_eq!(
+            patch,
+            "@@ -1,2 +1,2 @@\n\
+             -int main(){\n\
+             -  return 0;\n\
+             +int main() {\n\
+             +    return 0;\n\
+             @@ -6 +6 @@ int a;\n\
+             -int  b;\n\
+             +int b;"
+        );
+    }
+
+    #[test]
+    fn patch_without_changes() {
+        let original = "int a;\n";
+        let replacements = vec![replacement(0, 3, "int")];
+        assert!(make_patch(&PathBuf::from("demo.c"), original.as_bytes(), &replacements).is_none());
+    }
 }
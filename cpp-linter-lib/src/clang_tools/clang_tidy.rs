@@ -2,25 +2,87 @@
 //! output.
 
 use std::{
+    collections::{HashMap, HashSet},
     env::{consts::OS, current_dir},
-    path::PathBuf,
+    fs,
+    path::{Path, PathBuf},
     process::Command,
 };
 
 // non-std crates
 use regex::Regex;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 
 // project-specific modules/crates
-use crate::common_fs::{normalize_path, FileObj};
+use super::{ClangParams, ToolError};
+use crate::common_fs::{is_source_or_ignored, normalize_path, FileObj, PathSet};
 
 /// Used to deserialize a JSON compilation database
 #[derive(Deserialize, Debug)]
+#[serde(transparent)]
 pub struct CompilationDatabase {
     /// A list of [`CompilationUnit`]
     units: Vec<CompilationUnit>,
 }
 
+impl CompilationDatabase {
+    /// Read and parse the JSON compilation database from the given `database` path.
+    ///
+    /// The `database` path can be the directory that contains a `compile_commands.json`
+    /// file (as used for clang-tidy's `-p` option) or the path to the JSON file itself.
+    /// Returns [`None`] if the file could not be read or parsed.
+    pub fn from_path(database: &Path) -> Option<Self> {
+        let db_file = if database.is_dir() {
+            database.join("compile_commands.json")
+        } else {
+            database.to_path_buf()
+        };
+        let db_str = fs::read_to_string(&db_file).ok()?;
+        match serde_json::from_str::<CompilationDatabase>(&db_str) {
+            Ok(db_json) => Some(db_json),
+            Err(e) => {
+                log::error!("Failed to parse {:?}: {e}", db_file);
+                None
+            }
+        }
+    }
+
+    /// Lists the translation units in the database as a [`Vec<FileObj>`].
+    ///
+    /// Each unit's path is normalized (see [`normalize_path()`]) and made relative to the
+    /// repository root (the current working directory). Units outside of the repository
+    /// are skipped. The resulting list is filtered by the specified `extensions`,
+    /// `ignored` and `not_ignored` paths.
+    pub fn list_source_files(
+        &self,
+        extensions: &[&str],
+        ignored: &PathSet,
+        not_ignored: &PathSet,
+    ) -> Vec<FileObj> {
+        let repo_root = current_dir().unwrap();
+        let mut files: Vec<FileObj> = Vec::new();
+        for unit in &self.units {
+            let mut unit_path = PathBuf::from(&unit.directory).join(&unit.file);
+            if unit_path.is_relative() {
+                unit_path = repo_root.join(unit_path);
+            }
+            let unit_path = normalize_path(&unit_path);
+            if let Ok(file_name) = unit_path.strip_prefix(&repo_root) {
+                if files.iter().any(|f| f.name == file_name) {
+                    // a translation unit can be compiled multiple times (with different flags)
+                    continue;
+                }
+                if is_source_or_ignored(file_name, extensions, ignored, not_ignored) {
+                    files.push(FileObj::new(file_name.to_path_buf()));
+                }
+            } else {
+                log::debug!("Skipping {:?} because it is not in the repo", unit_path);
+            }
+        }
+        files
+    }
+}
+
 /// Used to deserialize a json compilation database's translation unit.
 ///
 /// The only purpose this serves is to normalize relative paths for build systems that
@@ -66,76 +128,116 @@ pub struct TidyNotification {
     /// Sometimes, this code block doesn't exist. Sometimes, it contains suggested
     /// fixes/advice. This information is purely superfluous.
     pub suggestion: Vec<String>,
+
+    /// The `note:` lines that clang-tidy attached to the notification (eg. "previous
+    /// declaration is here" or the steps of a clang-analyzer path).
+    pub related: Vec<RelatedLocation>,
+}
+
+/// A location related to a [`TidyNotification`], as described by a `note:` line in
+/// clang-tidy's stdout.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RelatedLocation {
+    /// The file's path and name (supposedly relative to the repository root folder).
+    pub filename: String,
+
+    /// The line number of the location.
+    pub line: u32,
+
+    /// The column offset on the line of the location.
+    pub cols: u32,
+
+    /// The note's message.
+    pub message: String,
+}
+
+/// Normalize the given `filename` (as reported by clang-tidy) and make it relative to
+/// the repository root (the current working directory) if possible.
+///
+/// Here it helps to have the JSON database deserialized, because a relative `filename`
+/// is relative to its translation unit's build directory.
+fn normalize_filename(filename: &str, database_json: &Option<CompilationDatabase>) -> String {
+    let mut path = PathBuf::from(filename);
+    if path.is_relative() {
+        // if database was given try to use that first
+        if let Some(db_json) = &database_json {
+            let mut found_unit = false;
+            for unit in &db_json.units {
+                if unit.file == filename {
+                    path = normalize_path(&PathBuf::from_iter([&unit.directory, &unit.file]));
+                    found_unit = true;
+                    break;
+                }
+            }
+            if !found_unit {
+                // file was not a named unit in the database;
+                // try to normalize path as if relative to working directory.
+                // NOTE: This shouldn't happen with a properly formed JSON database
+                path = normalize_path(&PathBuf::from_iter([&current_dir().unwrap(), &path]));
+            }
+        } else {
+            // still need to normalize the relative path despite missing database info.
+            // let's assume the file is relative to current working directory.
+            path = normalize_path(&PathBuf::from_iter([&current_dir().unwrap(), &path]));
+        }
+    }
+    assert!(path.is_absolute());
+    // a file outside of the repository (eg. a system header) keeps its absolute path
+    if let Ok(relative) = path.strip_prefix(current_dir().unwrap()) {
+        path = relative.to_path_buf();
+    }
+    path.to_string_lossy().to_string().replace('\\', "/")
 }
 
 /// Parses clang-tidy stdout.
 ///
 /// Here it helps to have the JSON database deserialized for normalizing paths present
 /// in the notifications.
+///
+/// A `note:` line (without a diagnostic name) is attached to the previous notification
+/// as a [`RelatedLocation`]. The code block that follows a `note:` line is not kept.
 fn parse_tidy_output(
     tidy_stdout: &[u8],
     database_json: &Option<CompilationDatabase>,
 ) -> Vec<TidyNotification> {
     let note_header = Regex::new(r"^(.+):(\d+):(\d+):\s(\w+):(.*)\[([a-zA-Z\d\-\.]+)\]$").unwrap();
-    let mut notification = None;
+    let related_header = Regex::new(r"^(.+):(\d+):(\d+):\snote:\s(.*)$").unwrap();
+    let mut notification: Option<TidyNotification> = None;
+    // are the following lines about a related location (not the notification)?
+    let mut in_related = false;
     let mut result = Vec::new();
     for line in String::from_utf8(tidy_stdout.to_vec()).unwrap().lines() {
         if let Some(captured) = note_header.captures(line) {
             if let Some(note) = notification {
                 result.push(note);
             }
-
-            // normalize the filename path and try to make it relative to the repo root
-            let mut filename = PathBuf::from(&captured[1]);
-            if filename.is_relative() {
-                // if database was given try to use that first
-                if let Some(db_json) = &database_json {
-                    let mut found_unit = false;
-                    for unit in &db_json.units {
-                        if unit.file == captured[0] {
-                            filename =
-                                normalize_path(&PathBuf::from_iter([&unit.directory, &unit.file]));
-                            found_unit = true;
-                            break;
-                        }
-                    }
-                    if !found_unit {
-                        // file was not a named unit in the database;
-                        // try to normalize path as if relative to working directory.
-                        // NOTE: This shouldn't happen with a properly formed JSON database
-                        filename = normalize_path(&PathBuf::from_iter([
-                            &current_dir().unwrap(),
-                            &filename,
-                        ]));
-                    }
-                } else {
-                    // still need to normalize the relative path despite missing database info.
-                    // let's assume the file is relative to current working directory.
-                    filename =
-                        normalize_path(&PathBuf::from_iter([&current_dir().unwrap(), &filename]));
-                }
-            }
-            assert!(filename.is_absolute());
-            if filename.is_absolute() {
-                filename = filename
-                    .strip_prefix(current_dir().unwrap())
-                    .expect("cannot determine filename by relative path.")
-                    .to_path_buf();
-            }
-
+            in_related = false;
             notification = Some(TidyNotification {
-                filename: filename.to_string_lossy().to_string().replace('\\', "/"),
+                filename: normalize_filename(&captured[1], database_json),
                 line: captured[2].parse::<u32>().unwrap(),
                 cols: captured[3].parse::<u32>().unwrap(),
                 severity: String::from(&captured[4]),
                 rationale: String::from(&captured[5]),
                 diagnostic: String::from(&captured[6]),
                 suggestion: Vec::new(),
+                related: Vec::new(),
             });
+        } else if let Some(captured) = related_header.captures(line) {
+            if let Some(note) = &mut notification {
+                in_related = true;
+                note.related.push(RelatedLocation {
+                    filename: normalize_filename(&captured[1], database_json),
+                    line: captured[2].parse::<u32>().unwrap(),
+                    cols: captured[3].parse::<u32>().unwrap(),
+                    message: captured[4].trim().to_string(),
+                });
+            }
         } else if let Some(note) = &mut notification {
-            // append lines of code that are part of
-            // the previous line's notification
-            note.suggestion.push(line.to_string());
+            if !in_related {
+                // append lines of code that are part of
+                // the previous line's notification
+                note.suggestion.push(line.to_string());
+            }
         }
     }
     if let Some(note) = notification {
@@ -144,41 +246,238 @@ fn parse_tidy_output(
     result
 }
 
+/// Make an entry of clang-tidy's `--line-filter` about the changed lines in the given
+/// `file`.
+fn make_line_filter(file: &FileObj, lines_changed_only: u8) -> String {
+    let ranges = file.get_ranges(lines_changed_only);
+    format!(
+        "{{\"name\":{:?},\"lines\":{:?}}}",
+        line_filter_name(file),
+        ranges
+            .iter()
+            .map(|r| [r.start(), r.end()])
+            .collect::<Vec<_>>()
+    )
+}
+
+/// The `file`'s name as clang-tidy's `--line-filter` expects it (with the OS' path
+/// separators).
+fn line_filter_name(file: &FileObj) -> String {
+    file.name
+        .to_string_lossy()
+        .replace('/', if OS == "windows" { "\\" } else { "/" })
+}
+
+/// The file extensions of headers (see [`make_header_line_filters()`]).
+const HEADER_EXTENSIONS: [&str; 9] = ["h", "hh", "hpp", "hxx", "h++", "inl", "ipp", "tpp", "cuh"];
+
+/// Make the `--line-filter` entries about the changed lines in the headers among the
+/// `files` (other than the analyzed `file`) that match the `header_filter` regex.
+///
+/// Only headers can have concerns reported because of the `header_filter`, so other
+/// files are not added. This keeps clang-tidy's command line short when there are
+/// many changed `files`. A header without line ranges (eg. given explicitly) is
+/// filtered in as a whole.
+fn make_header_line_filters(
+    file: &FileObj,
+    files: &[FileObj],
+    header_filter: &str,
+    lines_changed_only: u8,
+) -> Vec<String> {
+    let pattern = Regex::new(header_filter)
+        .map_err(|e| log::debug!("Matching all headers; failed to parse --header-filter: {e}"))
+        .ok();
+    let cur_dir = current_dir().unwrap_or_default();
+    files
+        .iter()
+        .filter(|other| {
+            let is_header = other
+                .name
+                .extension()
+                .and_then(|ext| ext.to_str())
+                .is_some_and(|ext| HEADER_EXTENSIONS.contains(&ext));
+            let is_match = match &pattern {
+                Some(pattern) => {
+                    let name = other.name.to_string_lossy().replace('\\', "/");
+                    let abs_name = cur_dir
+                        .join(&other.name)
+                        .to_string_lossy()
+                        .replace('\\', "/");
+                    pattern.is_match(&name) || pattern.is_match(&abs_name)
+                }
+                None => true,
+            };
+            other.name != file.name && is_header && is_match
+        })
+        .map(|other| {
+            if other.get_ranges(lines_changed_only).is_empty() {
+                // no known line ranges (eg. a header given without them);
+                // an entry without "lines" filters in the whole header
+                format!("{{\"name\":{:?}}}", line_filter_name(other))
+            } else {
+                make_line_filter(other, lines_changed_only)
+            }
+        })
+        .collect()
+}
+
+/// Move the notes in `tidy_advice` that are about another one of the `files` (eg. a
+/// changed header) to the list of notes about that file.
+///
+/// The `tidy_advice` is parallel to the `files`. A header's notes are reported by each
+/// translation unit that includes it, so the moved notes should be deduplicated
+/// afterward (see [`dedup_notes()`]). Notes about files that are not in `files` are
+/// not moved.
+pub fn attribute_notes(files: &[FileObj], tidy_advice: &mut [Vec<TidyNotification>]) {
+    let names = files
+        .iter()
+        .map(|file| file.name.to_string_lossy().replace('\\', "/"))
+        .collect::<Vec<_>>();
+    for index in 0..tidy_advice.len() {
+        let (own, others): (Vec<_>, Vec<_>) = tidy_advice[index]
+            .drain(..)
+            .partition(|note| note.filename == names[index]);
+        tidy_advice[index] = own;
+        for note in others {
+            match names.iter().position(|name| *name == note.filename) {
+                Some(target) => tidy_advice[target].push(note),
+                // not one of the analyzed files; keep it with the translation unit
+                None => tidy_advice[index].push(note),
+            }
+        }
+    }
+}
+
+/// The file, line, column, diagnostic name, and rationale that identify a
+/// [`TidyNotification`] (see [`dedup_notes()`]).
+type NoteKey = (String, u32, u32, String, String);
+
+/// Remove duplicate notes from `tidy_advice` (across all translation units).
+///
+/// Notes are duplicates if they have the same file, line, column, diagnostic name,
+/// and rationale. This happens when many translation units include the same header.
+/// The first note is kept, and the suggestions of its duplicates are appended to it
+/// (unless the same suggestion was already appended).
+///
+/// Returns the number of removed notes.
+pub fn dedup_notes(tidy_advice: &mut [Vec<TidyNotification>]) -> usize {
+    // the key of each kept note, its position in `tidy_advice`, and its merged suggestions
+    let mut seen: HashMap<NoteKey, (usize, usize, HashSet<Vec<String>>)> = HashMap::new();
+    let mut removed = 0;
+    for index in 0..tidy_advice.len() {
+        let notes = std::mem::take(&mut tidy_advice[index]);
+        for note in notes {
+            let key = (
+                note.filename.clone(),
+                note.line,
+                note.cols,
+                note.diagnostic.clone(),
+                note.rationale.clone(),
+            );
+            if let Some((first_index, position, merged)) = seen.get_mut(&key) {
+                if !note.suggestion.is_empty() && !merged.contains(&note.suggestion) {
+                    merged.insert(note.suggestion.clone());
+                    tidy_advice[*first_index][*position]
+                        .suggestion
+                        .extend(note.suggestion);
+                }
+                removed += 1;
+            } else {
+                let merged = HashSet::from([note.suggestion.clone()]);
+                seen.insert(key, (index, tidy_advice[index].len(), merged));
+                tidy_advice[index].push(note);
+            }
+        }
+    }
+    removed
+}
+
+/// The diagnostic name that clang-tidy uses for compilation errors.
+const COMPILATION_ERROR: &str = "clang-diagnostic-error";
+
+/// Remove the compilation errors (see [`COMPILATION_ERROR`]) from the given `notes`.
+///
+/// Compilation errors are reported as a [`ToolError`] instead of clang-tidy concerns,
+/// so they are not counted twice. Returns the removed errors formatted like clang's
+/// diagnostics (eg. `src/demo.cpp:1:10: error: 'demo.hpp' file not found`).
+fn take_compilation_errors(notes: &mut Vec<TidyNotification>) -> Vec<String> {
+    let errors = notes
+        .iter()
+        .filter(|note| note.diagnostic == COMPILATION_ERROR)
+        .map(|note| {
+            format!(
+                "{}:{}:{}: {}: {}",
+                note.filename, note.line, note.cols, note.severity, note.rationale
+            )
+        })
+        .collect();
+    notes.retain(|note| note.diagnostic != COMPILATION_ERROR);
+    errors
+}
+
+/// Describe why clang-tidy failed to analyze a file (if it did).
+///
+/// clang-tidy failed if it reported compilation errors (`clang-diagnostic-error`),
+/// could not process the file (eg. because the compilation database is missing), or
+/// exited with a non-zero status for any other reason than warnings treated as errors.
+fn get_tidy_error(success: bool, stderr: &str, notes: &[TidyNotification]) -> Option<&'static str> {
+    if notes
+        .iter()
+        .any(|note| note.diagnostic == COMPILATION_ERROR)
+    {
+        Some("clang-tidy reported compilation errors.")
+    } else if stderr.contains("Error while processing") {
+        Some("clang-tidy could not process the file.")
+    } else if !success && !stderr.contains("treated as error") {
+        Some("clang-tidy exited with a non-zero status.")
+    } else {
+        None
+    }
+}
+
 /// Run clang-tidy, then parse and return it's output.
+///
+/// If a [`ClangParams::header_filter`] is given, then the line filter (used for
+/// [`ClangParams::lines_changed_only`]) also includes the changed lines of the other
+/// `files` that are matching headers (see [`make_header_line_filters()`]), so that
+/// clang-tidy reports concerns about changed headers.
+///
+/// Also returns a [`ToolError`] if clang-tidy failed to analyze the `file` (see
+/// [`get_tidy_error()`]). Any compilation errors are only reported in that
+/// [`ToolError`] (see [`take_compilation_errors()`]).
 pub fn run_clang_tidy(
     cmd: &mut Command,
     file: &FileObj,
-    checks: &str,
-    lines_changed_only: u8,
+    files: &[FileObj],
+    params: &ClangParams,
     database: &Option<PathBuf>,
-    extra_args: &Option<Vec<&str>>,
     database_json: &Option<CompilationDatabase>,
-) -> Vec<TidyNotification> {
-    if !checks.is_empty() {
-        cmd.args(["-checks", checks]);
+) -> (Vec<TidyNotification>, Option<ToolError>) {
+    if !params.tidy_checks.is_empty() {
+        cmd.args(["-checks", &params.tidy_checks]);
     }
     if let Some(db) = database {
         cmd.args(["-p", &db.to_string_lossy()]);
     }
-    if let Some(extras) = extra_args {
+    if let Some(header_filter) = &params.header_filter {
+        cmd.arg(format!("--header-filter={header_filter}"));
+    }
+    if let Some(extras) = &params.extra_args {
         for arg in extras {
             cmd.args(["--extra-arg", format!("\"{}\"", arg).as_str()]);
         }
     }
-    if lines_changed_only > 0 {
-        let ranges = file.get_ranges(lines_changed_only);
-        let filter = format!(
-            "[{{\"name\":{:?},\"lines\":{:?}}}]",
-            &file
-                .name
-                .to_string_lossy()
-                .replace('/', if OS == "windows" { "\\" } else { "/" }),
-            ranges
-                .iter()
-                .map(|r| [r.start(), r.end()])
-                .collect::<Vec<_>>()
-        );
-        cmd.args(["--line-filter", filter.as_str()]);
+    if params.lines_changed_only > 0 {
+        let mut filter = vec![make_line_filter(file, params.lines_changed_only)];
+        if let Some(header_filter) = &params.header_filter {
+            filter.extend(make_header_line_filters(
+                file,
+                files,
+                header_filter,
+                params.lines_changed_only,
+            ));
+        }
+        cmd.args(["--line-filter", format!("[{}]", filter.join(",")).as_str()]);
     }
     cmd.arg(file.name.to_string_lossy().as_ref());
     log::info!(
@@ -194,17 +493,29 @@ pub fn run_clang_tidy(
         "Output from clang-tidy:\n{}",
         String::from_utf8(output.stdout.to_vec()).unwrap()
     );
-    if !output.stderr.is_empty() {
-        log::debug!(
-            "clang-tidy made the following summary:\n{}",
-            String::from_utf8(output.stderr).unwrap()
-        );
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    if !stderr.is_empty() {
+        log::debug!("clang-tidy made the following summary:\n{stderr}");
     }
-    parse_tidy_output(&output.stdout, database_json)
+    let mut notes = parse_tidy_output(&output.stdout, database_json);
+    let reason = get_tidy_error(output.status.success(), &stderr, &notes);
+    let mut details = take_compilation_errors(&mut notes);
+    details.push(stderr.to_string());
+    let error =
+        reason.map(|reason| ToolError::new("clang-tidy", file, reason, &details.join("\n")));
+    (notes, error)
 }
 
 #[cfg(test)]
 mod test {
+    use std::{env::current_dir, fs, path::PathBuf};
+
+    use super::{
+        attribute_notes, dedup_notes, get_tidy_error, make_header_line_filters, parse_tidy_output,
+        take_compilation_errors, CompilationDatabase, RelatedLocation, TidyNotification,
+    };
+    use crate::common_fs::{FileObj, PathSet};
+
     #[test]
     fn test_capture() {
         let src = "tests/demo/demo.hpp:11:11: warning: use a trailing return type for this function [modernize-use-trailing-return-type]";
@@ -225,4 +536,308 @@ mod test {
             .as_str()
         )
     }
+
+    fn make_note(diagnostic: &str) -> TidyNotification {
+        make_note_at("src/demo.cpp", 1, diagnostic)
+    }
+
+    fn make_note_at(filename: &str, line: u32, diagnostic: &str) -> TidyNotification {
+        TidyNotification {
+            filename: filename.to_string(),
+            line,
+            cols: 10,
+            severity: String::from("error"),
+            rationale: String::from("'demo.hpp' file not found"),
+            diagnostic: diagnostic.to_string(),
+            suggestion: vec![],
+            related: vec![],
+        }
+    }
+
+    #[test]
+    fn header_notes() {
+        let files = ["src/a.cpp", "src/b.cpp", "src/demo.hpp"]
+            .iter()
+            .map(|name| FileObj::new(PathBuf::from(name)))
+            .collect::<Vec<_>>();
+        let mut tidy_advice = vec![
+            vec![
+                make_note_at("src/a.cpp", 3, "bugprone-a"),
+                make_note_at("src/demo.hpp", 5, "bugprone-h"),
+                make_note_at("include/other.hpp", 1, "bugprone-o"),
+            ],
+            vec![
+                make_note_at("src/demo.hpp", 5, "bugprone-h"),
+                make_note_at("src/demo.hpp", 7, "bugprone-h"),
+            ],
+            // a header is not a translation unit
+            vec![],
+        ];
+        attribute_notes(&files, &mut tidy_advice);
+        assert_eq!(dedup_notes(&mut tidy_advice), 1);
+        let lines = |notes: &Vec<TidyNotification>| {
+            notes
+                .iter()
+                .map(|note| (note.filename.clone(), note.line))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            lines(&tidy_advice[0]),
+            vec![
+                (String::from("src/a.cpp"), 3),
+                (String::from("include/other.hpp"), 1)
+            ]
+        );
+        assert!(tidy_advice[1].is_empty());
+        // the same note from both translation units is only kept once
+        assert_eq!(
+            lines(&tidy_advice[2]),
+            vec![
+                (String::from("src/demo.hpp"), 5),
+                (String::from("src/demo.hpp"), 7)
+            ]
+        );
+    }
+
+    #[test]
+    fn related_notes() {
+        // a file outside of the repository
+        let system_header = std::env::temp_dir().join("stdio.h");
+        let system_header = system_header.to_string_lossy().replace('\\', "/");
+        let stdout = format!(
+            "\
+src/demo.cpp:5:6: warning: redundant redeclaration of 'f' [readability-redundant-declaration]
+    5 | void f();
+      | ~~~~~^
+src/demo.hpp:2:6: note: previous declaration is here
+    2 | void f();
+      |      ^
+{system_header}:10:1: note: in file included from here
+src/demo.cpp:9:3: warning: Called function pointer is null [clang-analyzer-core.CallAndMessage]
+    9 |   g();
+      |   ^
+"
+        );
+        let notes = parse_tidy_output(stdout.as_bytes(), &None);
+        assert_eq!(notes.len(), 2);
+        // the code blocks of related locations are not part of the suggestion
+        assert_eq!(
+            notes[0].suggestion,
+            vec!["    5 | void f();", "      | ~~~~~^"]
+        );
+        assert_eq!(
+            notes[0].related,
+            vec![
+                RelatedLocation {
+                    filename: String::from("src/demo.hpp"),
+                    line: 2,
+                    cols: 6,
+                    message: String::from("previous declaration is here"),
+                },
+                RelatedLocation {
+                    filename: system_header,
+                    line: 10,
+                    cols: 1,
+                    message: String::from("in file included from here"),
+                },
+            ]
+        );
+        assert_eq!(notes[1].diagnostic, "clang-analyzer-core.CallAndMessage");
+        assert_eq!(notes[1].suggestion.len(), 2);
+        assert!(notes[1].related.is_empty());
+    }
+
+    #[test]
+    fn duplicate_notes() {
+        let mut first = make_note_at("src/demo.hpp", 5, "bugprone-h");
+        first.suggestion = vec![String::from("int a;")];
+        let mut same = make_note_at("src/demo.hpp", 5, "bugprone-h");
+        same.suggestion = vec![String::from("int a;")];
+        let mut other_fix = make_note_at("src/demo.hpp", 5, "bugprone-h");
+        other_fix.suggestion = vec![String::from("long a;")];
+        let mut tidy_advice = vec![
+            vec![first, make_note_at("src/demo.hpp", 5, "bugprone-other")],
+            vec![same, make_note_at("src/demo.hpp", 6, "bugprone-h")],
+            vec![other_fix],
+        ];
+        assert_eq!(dedup_notes(&mut tidy_advice), 2);
+        assert_eq!(tidy_advice[0].len(), 2);
+        assert_eq!(
+            tidy_advice[0][0].suggestion,
+            vec![String::from("int a;"), String::from("long a;")]
+        );
+        assert_eq!(tidy_advice[1].len(), 1);
+        assert_eq!(tidy_advice[1][0].line, 6);
+        assert!(tidy_advice[2].is_empty());
+    }
+
+    #[test]
+    fn duplicate_fixes() {
+        // three translation units that include a header with the same fixes
+        let make_fixed_note = |fix: &str| {
+            let mut note = make_note_at("src/demo.hpp", 5, "bugprone-h");
+            note.suggestion = vec![String::from(fix)];
+            note
+        };
+        let mut tidy_advice = vec![
+            vec![make_fixed_note("int a;")],
+            vec![make_fixed_note("long a;")],
+            vec![make_fixed_note("long a;")],
+            vec![make_fixed_note("int a;")],
+        ];
+        assert_eq!(dedup_notes(&mut tidy_advice), 3);
+        assert_eq!(
+            tidy_advice[0][0].suggestion,
+            vec![String::from("int a;"), String::from("long a;")]
+        );
+    }
+
+    #[test]
+    fn header_line_filters() {
+        let files = vec![
+            FileObj::from_ranges(PathBuf::from("src/demo.cpp"), vec![1..=2]),
+            FileObj::from_ranges(PathBuf::from("src/other.cpp"), vec![3..=4]),
+            FileObj::from_ranges(PathBuf::from("src/demo.hpp"), vec![5..=6]),
+            FileObj::from_ranges(PathBuf::from("include/api.h"), vec![7..=7]),
+        ];
+        let filters = make_header_line_filters(&files[0], &files, ".*", 1);
+        assert_eq!(filters.len(), 2);
+        assert!(filters[0].contains("demo.hpp") && filters[0].contains("[5, 6]"));
+        assert!(filters[1].contains("api.h"));
+        let filters = make_header_line_filters(&files[0], &files, "src/", 1);
+        assert_eq!(filters.len(), 1);
+        assert!(filters[0].contains("demo.hpp"));
+        // an invalid regex matches all headers
+        assert_eq!(make_header_line_filters(&files[0], &files, "(", 1).len(), 2);
+    }
+
+    #[test]
+    fn header_line_filter_without_ranges() {
+        // eg. a header listed explicitly (without line ranges)
+        let files = vec![
+            FileObj::from_ranges(PathBuf::from("src/demo.cpp"), vec![1..=2]),
+            FileObj::new(PathBuf::from("src/listed.hpp")),
+        ];
+        let filters = make_header_line_filters(&files[0], &files, ".*", 1);
+        let name = PathBuf::from("src/listed.hpp");
+        assert_eq!(
+            filters,
+            vec![format!("{{\"name\":{:?}}}", name.to_string_lossy())]
+        );
+    }
+
+    #[test]
+    fn tidy_errors() {
+        assert!(get_tidy_error(true, "1 warning generated.", &[]).is_none());
+        let notes = vec![make_note("clang-diagnostic-error")];
+        assert_eq!(
+            get_tidy_error(false, "Found compiler error(s).", &notes),
+            Some("clang-tidy reported compilation errors.")
+        );
+        assert_eq!(
+            get_tidy_error(
+                false,
+                "Error while processing /src/demo.cpp.",
+                &[make_note("bugprone-demo")]
+            ),
+            Some("clang-tidy could not process the file.")
+        );
+        // warnings treated as errors are not a failure of clang-tidy
+        assert!(get_tidy_error(false, "2 warnings treated as errors", &[]).is_none());
+        assert_eq!(
+            get_tidy_error(false, "", &[]),
+            Some("clang-tidy exited with a non-zero status.")
+        );
+
+        // compilation errors are not counted as clang-tidy concerns
+        let mut notes = vec![
+            make_note("bugprone-demo"),
+            make_note("clang-diagnostic-error"),
+        ];
+        assert_eq!(
+            take_compilation_errors(&mut notes),
+            vec!["src/demo.cpp:1:10: error: 'demo.hpp' file not found"]
+        );
+        assert_eq!(notes.len(), 1);
+        assert_eq!(notes[0].diagnostic, "bugprone-demo");
+    }
+
+    #[test]
+    fn list_database_units() {
+        let tmp = tempfile::tempdir().unwrap();
+        let demo_dir = current_dir().unwrap().join("tests/demo");
+        let db = serde_json::json!([
+            {
+                "directory": demo_dir.to_string_lossy(),
+                "file": "demo.cpp",
+                "command": "clang++ -c demo.cpp"
+            },
+            {
+                "directory": demo_dir.to_string_lossy(),
+                "file": "./demo.cpp",
+                "command": "clang++ -DDEBUG -c demo.cpp"
+            },
+            {
+                "directory": demo_dir.join("build").to_string_lossy(),
+                "file": "../some source.cpp",
+                "command": "clang++ -c \"../some source.cpp\""
+            },
+            {
+                "directory": tmp.path().to_string_lossy(),
+                "file": "outside.cpp",
+                "command": "clang++ -c outside.cpp"
+            }
+        ]);
+        fs::write(
+            tmp.path().join("compile_commands.json"),
+            serde_json::to_string(&db).unwrap(),
+        )
+        .unwrap();
+
+        let db_json = CompilationDatabase::from_path(tmp.path()).unwrap();
+        let files = db_json.list_source_files(&["cpp"], &PathSet::default(), &PathSet::default());
+        let names = files
+            .iter()
+            .map(|f| f.name.to_string_lossy().replace('\\', "/"))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            names,
+            vec!["tests/demo/demo.cpp", "tests/demo/some source.cpp"]
+        );
+
+        let ignored = PathSet::new(vec!["./tests/demo/some*".to_string()]);
+        let files = db_json.list_source_files(&["cpp"], &ignored, &PathSet::default());
+        assert_eq!(files.len(), 1);
+    }
+
+    #[test]
+    fn missing_database() {
+        let tmp = tempfile::tempdir().unwrap();
+        assert!(CompilationDatabase::from_path(tmp.path()).is_none());
+    }
+
+    #[test]
+    fn database_shape() {
+        // a compilation database is a JSON array of translation units
+        let db = serde_json::from_str::<CompilationDatabase>(
+            r#"[{"directory": "/build", "file": "demo.cpp", "command": "clang++ -c demo.cpp"}]"#,
+        );
+        assert_eq!(db.unwrap().units.len(), 1);
+        assert!(serde_json::from_str::<CompilationDatabase>(r#"{"units": []}"#).is_err());
+    }
+
+    #[test]
+    fn match_database_unit() {
+        let demo_dir = current_dir().unwrap().join("tests/demo");
+        let db = serde_json::from_value::<CompilationDatabase>(serde_json::json!([{
+            "directory": demo_dir.to_string_lossy(),
+            "file": "demo.cpp",
+            "command": "clang++ -c demo.cpp"
+        }]))
+        .unwrap();
+        // the file name reported by clang-tidy is matched to the database's unit
+        let stdout = b"demo.cpp:1:1: warning: some concern [bugprone-demo]\n";
+        let notes = parse_tidy_output(stdout, &Some(db));
+        assert_eq!(notes[0].filename, "tests/demo/demo.cpp");
+    }
 }
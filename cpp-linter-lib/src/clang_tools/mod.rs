@@ -1,7 +1,7 @@
 //! This crate holds the functionality related to running clang-format and/or
 //! clang-tidy.
 
-use std::{env::current_dir, fs, path::PathBuf, process::Command};
+use std::{env::current_dir, path::PathBuf, process::Command};
 
 // non-std crates
 use lenient_semver;
@@ -10,11 +10,14 @@ use which::{which, which_in};
 
 // project-specific modules/crates
 use super::common_fs::FileObj;
+use crate::config::Config;
 use crate::logger::{end_log_group, start_log_group};
 pub mod clang_format;
 use clang_format::{run_clang_format, FormatAdvice};
 pub mod clang_tidy;
-use clang_tidy::{run_clang_tidy, CompilationDatabase, TidyNotification};
+use clang_tidy::{
+    attribute_notes, dedup_notes, run_clang_tidy, CompilationDatabase, TidyNotification,
+};
 
 /// Fetch the path to a clang tool by `name` (ie `"clang-tidy"` or `"clang-format"`) and
 /// `version`.
@@ -52,9 +55,9 @@ pub fn get_clang_tool_exe(name: &str, version: &str) -> Result<PathBuf, &'static
             // On Unix systems, this block is not likely reached. Typically, installing clang
             // will produce a symlink to the executable with the major version appended to the
             // name.
-            return Ok(cmd);
+            Ok(cmd)
         } else {
-            return Err("Could not find clang tool by name and version");
+            Err("Could not find clang tool by name and version")
         }
     } else {
         // `version` specified is not a semantic version; treat as path/to/bin
@@ -66,27 +69,112 @@ pub fn get_clang_tool_exe(name: &str, version: &str) -> Result<PathBuf, &'static
     }
 }
 
+/// The parameters used to run clang-tidy and/or clang-format on a single file.
+///
+/// These are initially derived from CLI options, but they can be altered for specific
+/// files by a [`ConfigOverride`](crate::config::ConfigOverride).
+#[derive(Debug, Clone, PartialEq)]
+pub struct ClangParams {
+    /// The value of the `--tidy-checks` option.
+    pub tidy_checks: String,
+
+    /// The value of the `--style` option.
+    pub style: String,
+
+    /// The value of the `--lines-changed-only` option.
+    pub lines_changed_only: u8,
+
+    /// The value(s) of the `--extra-arg` option.
+    pub extra_args: Option<Vec<String>>,
+
+    /// The value of the `--header-filter` option.
+    pub header_filter: Option<String>,
+}
+
+/// A failure of a clang tool to analyze a file.
+///
+/// This describes a crash (a non-zero exit status) or errors that prevented the tool
+/// from analyzing the file (eg. compilation errors or a missing compilation database).
+/// In such cases, the tool's output likely misses concerns about the file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ToolError {
+    /// The name of the tool (ie `"clang-tidy"` or `"clang-format"`).
+    pub tool: String,
+
+    /// The analyzed file's path and name (relative to the repository root).
+    pub filename: String,
+
+    /// A description of the failure (usually the tool's error output).
+    pub message: String,
+}
+
+/// The maximum number of lines kept from a tool's error output in a [`ToolError`].
+const MAX_ERROR_LINES: usize = 20;
+
+impl ToolError {
+    /// Create a [`ToolError`] about the given `file` for the given `reason`.
+    ///
+    /// The first few lines of the `tool`'s error output (`stderr`) are appended to the
+    /// `reason`.
+    pub fn new(tool: &str, file: &FileObj, reason: &str, stderr: &str) -> Self {
+        let lines = stderr
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .collect::<Vec<_>>();
+        let mut message = reason.to_string();
+        for line in lines.iter().take(MAX_ERROR_LINES) {
+            message.push('\n');
+            message.push_str(line);
+        }
+        if lines.len() > MAX_ERROR_LINES {
+            message.push_str(&format!(
+                "\n... ({} more line(s))",
+                lines.len() - MAX_ERROR_LINES
+            ));
+        }
+        ToolError {
+            tool: tool.to_string(),
+            filename: file.name.to_string_lossy().replace('\\', "/"),
+            message,
+        }
+    }
+}
+
 /// Runs clang-tidy and/or clang-format and returns the parsed output from each.
 ///
 /// The returned list of [`FormatAdvice`] is parallel to the `files` list passed in
 /// here. The returned 2D list of [`TidyNotification`] is also parallel on the first
 /// dimension. The second dimension is a list of notes specific to a translation unit
-/// (each element of `files`).
+/// (each element of `files`). The returned list of [`ToolError`]s describes the files
+/// that a tool failed to analyze.
 ///
-/// If `tidy_checks` is `"-*"` then clang-tidy is not executed.
-/// If `style` is a blank string (`""`), then clang-format is not executed.
+/// The given `clang_params` are altered for each file according to the `config`.
+/// Clang-tidy's notes about any other one of the `files` (eg. a changed header shown
+/// because of a header filter) are moved to the list of notes about that file (see
+/// [`attribute_notes()`]). Duplicate clang-tidy notes (eg. about a header included
+/// by many of the `files`) are only reported once (see [`dedup_notes()`]).
+/// If the resulting `tidy_checks` is `"-*"` then clang-tidy is not executed.
+/// If the resulting `style` is a blank string (`""`), then clang-format is not executed.
 pub fn capture_clang_tools_output(
-    files: &Vec<FileObj>,
+    files: &[FileObj],
     version: &str,
-    tidy_checks: &str,
-    style: &str,
-    lines_changed_only: u8,
+    clang_params: &ClangParams,
     database: Option<PathBuf>,
-    extra_args: Option<Vec<&str>>,
-) -> (Vec<FormatAdvice>, Vec<Vec<TidyNotification>>) {
+    config: &Config,
+) -> (
+    Vec<FormatAdvice>,
+    Vec<Vec<TidyNotification>>,
+    Vec<ToolError>,
+) {
+    // resolve the parameters for each file
+    let files_params = files
+        .iter()
+        .map(|file| config.get_params(&file.name, clang_params))
+        .collect::<Vec<_>>();
+
     // find the executable paths for clang-tidy and/or clang-format and show version
     // info as debugging output.
-    let clang_tidy_command = if tidy_checks != "-*" {
+    let clang_tidy_command = if files_params.iter().any(|p| p.tidy_checks != "-*") {
         let cmd = get_clang_tool_exe("clang-tidy", version).unwrap();
         log::debug!(
             "{} --version\n{}",
@@ -97,7 +185,7 @@ pub fn capture_clang_tools_output(
     } else {
         None
     };
-    let clang_format_command = if !style.is_empty() {
+    let clang_format_command = if files_params.iter().any(|p| !p.style.is_empty()) {
         let cmd = get_clang_tool_exe("clang-format", version).unwrap();
         log::debug!(
             "{} --version\n{}",
@@ -111,16 +199,7 @@ pub fn capture_clang_tools_output(
 
     // parse database (if provided) to match filenames when parsing clang-tidy's stdout
     let database_json: Option<CompilationDatabase> = if let Some(db_path) = &database {
-        if let Ok(db_str) = fs::read(db_path) {
-            Some(
-                serde_json::from_str::<CompilationDatabase>(
-                    String::from_utf8(db_str).unwrap().as_str(),
-                )
-                .unwrap(),
-            )
-        } else {
-            None
-        }
+        CompilationDatabase::from_path(db_path)
     } else {
         None
     };
@@ -129,30 +208,65 @@ pub fn capture_clang_tools_output(
     let mut all_format_advice: Vec<clang_format::FormatAdvice> = Vec::with_capacity(files.len());
     let mut all_tidy_advice: Vec<Vec<clang_tidy::TidyNotification>> =
         Vec::with_capacity(files.len());
-    for file in files {
+    let mut tool_errors: Vec<ToolError> = Vec::new();
+    for (file, params) in files.iter().zip(&files_params) {
         start_log_group(format!("Analyzing {}", file.name.to_string_lossy()));
         if let Some(tidy_cmd) = &clang_tidy_command {
-            all_tidy_advice.push(run_clang_tidy(
-                &mut Command::new(tidy_cmd),
-                file,
-                tidy_checks,
-                lines_changed_only,
-                &database,
-                &extra_args,
-                &database_json,
-            ));
+            // keep the results parallel to `files` even if a file's override disabled clang-tidy
+            all_tidy_advice.push(if params.tidy_checks != "-*" {
+                let (notes, error) = run_clang_tidy(
+                    &mut Command::new(tidy_cmd),
+                    file,
+                    files,
+                    params,
+                    &database,
+                    &database_json,
+                );
+                tool_errors.extend(error);
+                notes
+            } else {
+                Vec::new()
+            });
         }
         if let Some(format_cmd) = &clang_format_command {
-            all_format_advice.push(run_clang_format(
-                &mut Command::new(format_cmd),
-                file,
-                style,
-                lines_changed_only,
-            ));
+            all_format_advice.push(if !params.style.is_empty() {
+                let (advice, error) = run_clang_format(
+                    &mut Command::new(format_cmd),
+                    file,
+                    &params.style,
+                    params.lines_changed_only,
+                );
+                tool_errors.extend(error);
+                advice
+            } else {
+                FormatAdvice::default()
+            });
         }
         end_log_group();
     }
-    (all_format_advice, all_tidy_advice)
+    // clang-tidy's header filter may also come from a .clang-tidy config file,
+    // so look for notes about files other than the translation unit
+    let has_foreign_notes = all_tidy_advice.iter().zip(files).any(|(notes, file)| {
+        let name = file.name.to_string_lossy().replace('\\', "/");
+        notes.iter().any(|note| note.filename != name)
+    });
+    if has_foreign_notes {
+        // diagnostics about headers are reported by the translation units that include them
+        attribute_notes(files, &mut all_tidy_advice);
+    }
+    let duplicates = dedup_notes(&mut all_tidy_advice);
+    if duplicates > 0 {
+        log::debug!("Removed {duplicates} duplicate clang-tidy notification(s)");
+    }
+    for error in &tool_errors {
+        log::error!(
+            "{} failed to analyze {}:\n{}",
+            error.tool,
+            error.filename,
+            error.message
+        );
+    }
+    (all_format_advice, all_tidy_advice, tool_errors)
 }
 
 #[cfg(test)]
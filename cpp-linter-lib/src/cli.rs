@@ -1,10 +1,16 @@
 //! This module holds the Command Line Interface design.
 
+use std::env::current_dir;
 use std::fs;
+use std::ops::RangeInclusive;
+use std::path::PathBuf;
 
 // non-std crates
 use clap::builder::FalseyValueParser;
-use clap::{Arg, ArgAction, ArgMatches, Command};
+use clap::{value_parser, Arg, ArgAction, ArgMatches, Command};
+
+// project specific modules/crates
+use crate::common_fs::{FileObj, PathSet};
 
 /// Builds and returns the Command Line Interface's argument parsing object.
 pub fn get_arg_parser() -> Command {
@@ -72,6 +78,24 @@ option in a .clang-tidy file (if any).
   specifying this option as a blank string (``''``).
 
 See also clang-tidy docs for more info.
+",
+                ),
+        )
+        .arg(
+            Arg::new("header-filter")
+                .long("header-filter")
+                .long_help(
+                    "A regular expression that is passed to clang-tidy's
+``--header-filter`` option. clang-tidy reports concerns about the headers
+(included by an analyzed file) that match this expression.
+
+Concerns about headers are only reported if the header is also one of the
+analyzed files (eg. a changed header). Such concerns respect the header's
+own changed lines (see :std:option:`--lines-changed-only`), and they are
+reported only once (even if the header is included by many files).
+
+By default, clang-tidy uses the ``HeaderFilterRegex`` option from a
+.clang-tidy config file (if any).
 ",
                 ),
         )
@@ -131,8 +155,112 @@ the current working directory if not using a CI runner).
   with a ``.``) are also ignored automatically.
 - Prefix a path with ``!`` to explicitly not ignore it. This can be
   applied to a submodule's path (if desired) but not hidden directories.
-- Glob patterns are not supported here. All asterisk characters (``*``)
-  are literal.
+- Glob patterns are supported here (eg. ``**/generated/*.pb.h`` or
+  ``src/**/test_*.cpp``). A ``*`` does not match a path separator (``/``),
+  but ``**`` matches any number of directories. All patterns are relative
+  to the :std:option:`--repo-root`, and they do not need to exist on disk.
+",
+                ),
+        )
+        .arg(
+            Arg::new("diff-base")
+                .long("diff-base")
+                .short('b')
+                .long_help(
+                    "A git ref (eg. a branch name, tag, or SHA) to compare against when
+getting the list of changed files. The diff is computed locally from the
+merge-base of this ref and :std:option:`--diff-head`, which reproduces
+the changes that a pull request would introduce.
+
+Setting this option enables :std:option:`--files-changed-only`, and the
+REST API is not used to get the list of changed files.
+",
+                ),
+        )
+        .arg(
+            Arg::new("diff-head")
+                .long("diff-head")
+                .short('H')
+                .requires("diff-base")
+                .long_help(
+                    "A git ref to compare with the :std:option:`--diff-base`. Defaults
+to ``HEAD`` if not specified.
+",
+                ),
+        )
+        .arg(
+            Arg::new("diff-workdir")
+                .long("diff-workdir")
+                .short('W')
+                .value_parser(["false", "head", "index"])
+                .default_value("false")
+                .conflicts_with("diff-base")
+                .long_help(
+                    "Use this option to analyze the changes in the working directory
+(including untracked source files) before they are committed.
+The following values are accepted:
+
+- ``false``: The working directory is not used.
+- ``head``: Compare the working directory to ``HEAD`` (includes staged
+  and unstaged changes).
+- ``index``: Compare the working directory to the index (includes only
+  unstaged changes).
+
+Setting this option to ``head`` or ``index`` enables
+:std:option:`--files-changed-only`, and the REST API is not used to get
+the list of changed files.
+",
+                ),
+        )
+        .arg(
+            Arg::new("files-from-database")
+                .short('D')
+                .long("files-from-database")
+                .default_value("false")
+                .value_parser(FalseyValueParser::new())
+                .long_help(
+                    "Set this option to true to only analyze the translation units listed
+in the compilation database (see :std:option:`--database`). This ensures
+clang-tidy only analyzes files with proper compile flags.
+
+The listed files are still filtered by :std:option:`--extensions` and
+:std:option:`--ignore`. Files outside of the :std:option:`--repo-root` are
+skipped.
+
+This option only applies when :std:option:`--files-changed-only` and
+:std:option:`--lines-changed-only` are disabled.
+",
+                ),
+        )
+        .arg(
+            Arg::new("tracked-only")
+                .short('T')
+                .long("tracked-only")
+                .default_value("false")
+                .value_parser(FalseyValueParser::new())
+                .long_help(
+                    "Set this option to true to only analyze source files that are
+tracked by git (listed in the repository's index). This is much faster than
+walking the entire :std:option:`--repo-root` on large checkouts.
+
+This option only applies when :std:option:`--files-changed-only` and
+:std:option:`--lines-changed-only` are disabled.
+",
+                ),
+        )
+        .arg(
+            Arg::new("respect-gitignore")
+                .short('G')
+                .long("respect-gitignore")
+                .default_value("false")
+                .value_parser(FalseyValueParser::new())
+                .long_help(
+                    "Set this option to true to skip any files (or directories) that
+are ignored by the repository's ``.gitignore`` rules. This is useful to avoid
+analyzing build directories, vendored sources, or generated files.
+
+This option only applies when :std:option:`--files-changed-only` and
+:std:option:`--lines-changed-only` are disabled.
 ",
                 ),
         )
@@ -194,7 +322,7 @@ avoid using spaces between name and value (use ``=`` instead):
             Arg::new("thread-comments")
                 .long("thread-comments")
                 .short('g')
-                .value_parser(["true", "false", "updated"])
+                .value_parser(["true", "false", "update"])
                 .default_value("false")
                 .long_help(
                     "Set this option to true to enable the use of thread comments as feedback.
@@ -213,6 +341,26 @@ the value 'true' will always delete an old comment and post a new one if necessa
     If run on a private repository, then this feature is
     disabled because the GitHub REST API behaves
     differently for thread comments on a private repository.
+",
+                ),
+        )
+        .arg(
+            Arg::new("comment-key")
+                .long("comment-key")
+                .default_value("")
+                .long_help(
+                    "A key that identifies the thread comment posted by this run.
+
+Outdated thread comments are only updated or removed if they were
+posted with the same key. Use a different key for each job that
+posts a thread comment in the same pull request (eg. jobs on
+different platforms), so that their reports do not replace each other.
+
+.. note::
+    Thread comments are also only updated or removed if they were
+    posted by the same account that owns the ``GITHUB_TOKEN``. If that
+    account cannot be identified (eg. with the token provided to
+    workflows), then it is assumed to be ``github-actions[bot]``.
 ",
                 ),
         )
@@ -240,6 +388,11 @@ thread comment that basically says 'Looks Good To Me' (when all checks pass).
                 .long_help(
                     "Set this option to true or false to enable or disable the use of
 a workflow step summary when the run has concluded.
+
+.. note::
+    If the thread comment (or check run summary) is too long and must
+    be truncated, then the full report is always written to the step
+    summary (and linked from the truncated comment).
 ",
                 ),
         )
@@ -252,6 +405,259 @@ a workflow step summary when the run has concluded.
                 .long_help(
                     "Set this option to false to disable the use of
 file annotations as feedback.
+",
+                ),
+        )
+        .arg(
+            Arg::new("fail-severity")
+                .long("fail-severity")
+                .value_parser(["note", "warning", "error"])
+                .default_value("note")
+                .long_help(
+                    "The minimum severity of a clang-tidy concern that counts as a failure
+(in ``tidy-checks-failed``). For example, use ``error`` to only fail
+on clang-tidy errors. Other concerns are still reported, but they are
+only informational.
+
+.. seealso::
+    The :std:option:`--blocking-checks` and :std:option:`--max-warnings`
+    options also decide which concerns count as failures.
+",
+                ),
+        )
+        .arg(
+            Arg::new("blocking-checks")
+                .long("blocking-checks")
+                .long_help(
+                    "A comma-separated list of glob patterns that describe the clang-tidy
+checks that count as failures (eg. ``bugprone-*,cert-*``). Concerns from
+other checks are still reported, but they are only informational.
+
+By default, all checks count as failures.
+
+.. note::
+    A concern must also satisfy the :std:option:`--fail-severity` to
+    count as a failure.
+",
+                ),
+        )
+        .arg(
+            Arg::new("max-warnings")
+                .long("max-warnings")
+                .value_parser(value_parser!(u32))
+                .long_help(
+                    "The maximum number of informational clang-tidy concerns (those
+that are not failures because of :std:option:`--fail-severity` or
+:std:option:`--blocking-checks`). If there are more, then all of them
+count as failures.
+
+By default, there is no limit.
+",
+                ),
+        )
+        .arg(
+            Arg::new("baseline")
+                .long("baseline")
+                .long_help(
+                    "The path to a baseline file (created with
+:std:option:`--baseline-create`). Concerns recorded in the baseline are
+not reported, and they do not count toward ``checks-failed``.
+
+Concerns are matched by file, diagnostic name, and the content of the
+concerned line. So, a recorded concern still matches if its line was
+moved (eg. by adding lines above it).
+",
+                ),
+        )
+        .arg(
+            Arg::new("baseline-create")
+                .long("baseline-create")
+                .long_help(
+                    "The path of a baseline file to create. All concerns found in this run
+are recorded in the baseline file (before any concerns are suppressed
+by the :std:option:`--baseline` option).
+
+This is useful for adopting new clang-tidy checks in an existing code
+base, so that only new concerns are reported in subsequent runs.
+",
+                ),
+        )
+        .arg(
+            Arg::new("comment-template")
+                .long("comment-template")
+                .long_help(
+                    "The path to a `Handlebars <https://handlebarsjs.com/guide/>`_ template
+used to render the report. The report is used for thread comments, step
+summaries, and check run summaries. By default, a built-in template is used.
+
+The template has access to the following data:
+
+- ``marker``: The hidden marker that identifies the thread comment. This
+  should be the first line of the rendered report.
+- ``checks_failed``, ``format_checks_failed``, ``tidy_checks_failed``:
+  The counts of concerns that count as failures (see
+  :std:option:`--fail-severity`).
+- ``tidy_concerns``: The count of all clang-tidy concerns (including
+  informational ones).
+- ``format_files``: The list of files that are not formatted. Each has a
+  ``name`` and a ``patch`` (a diff of the changes clang-format wants to
+  make, if available).
+- ``tidy_notes``: The list of clang-tidy concerns. Each has a
+  ``filename``, ``line``, ``cols``, ``severity``, ``diagnostic``,
+  ``rationale``, ``suggestion``, and ``extension``.
+- ``tidy_files``: The clang-tidy concerns grouped by file. Each has a
+  ``name``, ``count``, and list of ``notes`` (like ``tidy_notes``).
+- ``tidy_summary``: The counts of clang-tidy concerns. Each has a
+  ``diagnostic``, ``severity``, and ``count``.
+- ``truncated``, ``format_omitted``, ``tidy_omitted``, ``full_report_url``:
+  Information about concerns that were omitted because the report
+  was too long.
+",
+                ),
+        )
+        .arg(
+            Arg::new("check-run")
+                .long("check-run")
+                .value_parser(FalseyValueParser::new())
+                .default_value("false")
+                .long_help(
+                    "Set this option to true to create a check run (using the Checks API)
+as feedback. The check run's summary is the same as the thread comment,
+and each concern is reported as an annotation on the check run.
+
+Unlike :std:option:`--file-annotations`, the number of annotations is
+not limited, and each clang-tidy annotation includes the suggested fix
+(if any).
+
+.. note::
+    The ``GITHUB_TOKEN`` must have the ``checks: write`` permission.
+",
+                ),
+        )
+        .arg(
+            Arg::new("check-failure-threshold")
+                .long("check-failure-threshold")
+                .value_parser(value_parser!(u32))
+                .default_value("1")
+                .long_help(
+                    "The number of concerns at which the check run's conclusion is
+``failure``. If there are fewer concerns (but at least one), then the
+conclusion is ``neutral``. Without any concerns, the conclusion is
+``success``.
+
+Set this to ``0`` to never conclude with ``failure``.
+
+.. seealso::
+    This only applies to the :std:option:`--check-run` option.
+",
+                ),
+        )
+        .arg(
+            Arg::new("fail-on-tool-errors")
+                .long("fail-on-tool-errors")
+                .value_parser(FalseyValueParser::new())
+                .default_value("true")
+                .long_help(
+                    "Set this option to false to not count the files that clang-tidy or
+clang-format failed to analyze as failed checks. A tool fails to analyze
+a file if it crashes (exits with a non-zero status) or reports errors
+that prevent the analysis (eg. compilation errors or a missing
+compilation database).
+
+Such failures are always reported (separately from other concerns),
+because the tool's concerns about the file are likely incomplete.
+",
+                ),
+        )
+        .arg(
+            Arg::new("exit-code")
+                .long("exit-code")
+                .value_parser(["always-zero", "fail-on-any", "fail-on-threshold"])
+                .default_value("always-zero")
+                .long_help(
+                    "Decides the exit status of cpp-linter based on the total number of
+``checks-failed``:
+
+- ``always-zero`` always exits with status ``0``. The results can still
+  be gated on with the ``checks-failed`` output variable (in GitHub
+  Actions).
+- ``fail-on-any`` exits with status ``1`` if there are any failed checks.
+- ``fail-on-threshold`` exits with status ``1`` if the number of failed
+  checks is at least the :std:option:`--exit-code-threshold`.
+
+This is useful when cpp-linter is not run in GitHub Actions (eg. as a
+pre-commit hook or in a Makefile target).
+",
+                ),
+        )
+        .arg(
+            Arg::new("exit-code-threshold")
+                .long("exit-code-threshold")
+                .value_parser(value_parser!(u32))
+                .default_value("1")
+                .long_help(
+                    "The number of failed checks at which cpp-linter exits with a non-zero
+status.
+
+.. seealso::
+    This only applies when :std:option:`--exit-code` is
+    ``fail-on-threshold``.
+",
+                ),
+        )
+        .arg(
+            Arg::new("files")
+                .action(ArgAction::Append)
+                .num_args(0..)
+                .long_help(
+                    "An explicit list of files to analyze. When specified, the files are
+not discovered by walking the :std:option:`--repo-root` or by getting
+the list of changed files.
+
+Each file can be followed by a colon and a comma-separated list of line
+ranges (eg. ``src/file.cpp:10-20,42``). These ranges are only used when
+:std:option:`--lines-changed-only` is enabled. A file without any line
+ranges is analyzed entirely.
+
+The listed files are still filtered by :std:option:`--extensions` and
+:std:option:`--ignore`.
+",
+                ),
+        )
+        .arg(
+            Arg::new("files-from")
+                .long("files-from")
+                .long_help(
+                    "The path to a file that contains a list of files to analyze (one
+per line). Set this to ``-`` to read the list from stdin. The format of
+each line is the same as the positional ``files`` arguments.
+",
+                ),
+        )
+        .arg(
+            Arg::new("config")
+                .long("config")
+                .short('C')
+                .default_value(".cpp-linter.toml")
+                .long_help(
+                    "The path to a TOML configuration file (relative to the
+:std:option:`--repo-root`). If the file does not exist, then it is ignored.
+
+The configuration file can contain ``[[overrides]]`` sections to change the
+:std:option:`--style`, :std:option:`--tidy-checks`,
+:std:option:`--lines-changed-only`, and :std:option:`--extra-arg` values for
+files that match a list of glob ``paths``:
+
+.. code-block:: toml
+
+    [[overrides]]
+    paths = [\"legacy/**\"]
+    style = \"file\"
+    tidy-checks = \"-*,bugprone-*\"
+    lines-changed-only = false
+    extra-args = [\"-std=c++11\"]
+
+Overrides are applied in order of appearance.
 ",
                 ),
         )
@@ -260,7 +666,7 @@ file annotations as feedback.
 /// This will parse the list of paths specified from the CLI using the `--ignore`
 /// argument.
 ///
-/// It returns 2 lists (in order):
+/// It returns 2 compiled sets of paths (in order):
 ///
 /// - `ignored` paths
 /// - `not_ignored` paths
@@ -268,7 +674,7 @@ file annotations as feedback.
 /// This function will also read a .gitmodules file located in the working directory.
 /// The named submodules' paths will be automatically added to the ignored list,
 /// unless the submodule's path is already specified in the not_ignored list.
-pub fn parse_ignore(ignore: &[&str]) -> (Vec<String>, Vec<String>) {
+pub fn parse_ignore(ignore: &[&str]) -> (PathSet, PathSet) {
     let mut ignored = vec![];
     let mut not_ignored = vec![];
     for pattern in ignore {
@@ -293,7 +699,7 @@ pub fn parse_ignore(ignore: &[&str]) -> (Vec<String>, Vec<String>) {
         for line in read_buf.split('\n') {
             if line.trim_start().starts_with("path") {
                 assert!(line.find('=').unwrap() > 0);
-                let submodule = String::from("./") + line.split('=').last().unwrap().trim();
+                let submodule = String::from("./") + line.split('=').next_back().unwrap().trim();
                 log::debug!("Found submodule: {submodule}");
                 let mut is_ignored = true;
                 for pat in &not_ignored {
@@ -321,7 +727,78 @@ pub fn parse_ignore(ignore: &[&str]) -> (Vec<String>, Vec<String>) {
             log::info!("  {pattern}");
         }
     }
-    (ignored, not_ignored)
+    (PathSet::new(ignored), PathSet::new(not_ignored))
+}
+
+/// Parses a list of file specifications as given to the positional `files` arguments
+/// or the `--files-from` option.
+///
+/// Each entry can be a file's path optionally followed by a colon and a comma-separated
+/// list of line ranges (eg. `src/file.cpp:10-20,42`). Entries that describe the same
+/// file are merged. Blank entries are skipped.
+pub fn parse_file_list(entries: &[&str]) -> Vec<FileObj> {
+    let mut files: Vec<(PathBuf, Vec<RangeInclusive<u32>>)> = Vec::new();
+    for entry in entries {
+        let entry = entry.trim();
+        if entry.is_empty() {
+            continue;
+        }
+        let (name, ranges) = match entry.rsplit_once(':') {
+            Some((name, suffix)) if !name.is_empty() => match parse_line_ranges(suffix) {
+                Some(ranges) => (name, ranges),
+                None => (entry, Vec::new()),
+            },
+            _ => (entry, Vec::new()),
+        };
+        let mut file_name = PathBuf::from(name.replace('\\', "/"));
+        if let Ok(rel_path) = file_name.strip_prefix("./") {
+            file_name = rel_path.to_path_buf();
+        } else if let Some(rel_path) = current_dir().ok().and_then(|cur_dir| {
+            file_name
+                .strip_prefix(cur_dir)
+                .ok()
+                .map(|p| p.to_path_buf())
+        }) {
+            file_name = rel_path;
+        }
+        if let Some((_, known_ranges)) = files.iter_mut().find(|(n, _)| n == &file_name) {
+            if known_ranges.is_empty() || ranges.is_empty() {
+                // the whole file shall be analyzed
+                known_ranges.clear();
+            } else {
+                known_ranges.extend(ranges);
+            }
+        } else {
+            files.push((file_name, ranges));
+        }
+    }
+    files
+        .into_iter()
+        .map(|(name, ranges)| {
+            if ranges.is_empty() {
+                FileObj::new(name)
+            } else {
+                FileObj::from_ranges(name, ranges)
+            }
+        })
+        .collect()
+}
+
+/// Parses a comma-separated list of line ranges (eg. `10-20,42`).
+///
+/// Returns [`None`] if the given `value` is not a valid list of line ranges.
+fn parse_line_ranges(value: &str) -> Option<Vec<RangeInclusive<u32>>> {
+    let mut ranges = Vec::new();
+    for range in value.split(',') {
+        let (start, end) = range.split_once('-').unwrap_or((range, range));
+        let start = start.trim().parse::<u32>().ok()?;
+        let end = end.trim().parse::<u32>().ok()?;
+        if start == 0 || end < start {
+            return None;
+        }
+        ranges.push(start..=end);
+    }
+    Some(ranges)
 }
 
 /// Converts the parsed value of the `--extra-arg` option into an optional vector of strings.
@@ -373,7 +850,7 @@ pub fn convert_extra_arg_val(args: &ArgMatches) -> Option<Vec<&str>> {
 mod test {
     use clap::ArgMatches;
 
-    use super::{convert_extra_arg_val, get_arg_parser};
+    use super::{convert_extra_arg_val, get_arg_parser, parse_file_list};
 
     fn parser_args(input: Vec<&str>) -> ArgMatches {
         let arg_parser = get_arg_parser();
@@ -412,4 +889,38 @@ mod test {
             assert_eq!(extra_args, ["-std=c++17", "-Wall"])
         }
     }
+
+    #[test]
+    fn file_list() {
+        let args = parser_args(vec![
+            "cpp-linter",
+            "src/a.cpp:10-20,42",
+            "./src/b.cpp",
+            "src/a.cpp:30-31",
+            "C:\\src\\c.cpp",
+            "src/d.cpp:bad",
+        ]);
+        let entries = args
+            .get_many::<String>("files")
+            .unwrap()
+            .map(|s| s.as_str())
+            .collect::<Vec<_>>();
+        let files = parse_file_list(&entries);
+        assert_eq!(files.len(), 4);
+        assert_eq!(files[0].name.to_string_lossy(), "src/a.cpp");
+        assert_eq!(files[0].added_ranges, vec![10..=20, 30..=31, 42..=42]);
+        assert_eq!(files[0].diff_chunks, files[0].added_ranges);
+        assert_eq!(files[1].name.to_string_lossy(), "src/b.cpp");
+        assert!(files[1].added_ranges.is_empty());
+        assert_eq!(files[2].name.to_string_lossy(), "C:/src/c.cpp");
+        assert!(files[2].added_ranges.is_empty());
+        assert_eq!(files[3].name.to_string_lossy(), "src/d.cpp:bad");
+    }
+
+    #[test]
+    fn file_list_whole_file() {
+        let files = parse_file_list(&["src/a.cpp:1-2", "src/a.cpp", "", "src/a.cpp:5"]);
+        assert_eq!(files.len(), 1);
+        assert!(files[0].added_ranges.is_empty());
+    }
 }
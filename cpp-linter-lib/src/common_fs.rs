@@ -1,10 +1,15 @@
 //! A module to hold all common file system functionality.
 
+use std::cell::Cell;
 use std::io::Read;
 use std::path::{Component, Path};
 use std::{fs, io};
 use std::{ops::RangeInclusive, path::PathBuf};
 
+// non-std crates
+use git2::Repository;
+use globset::{GlobBuilder, GlobSet, GlobSetBuilder};
+
 /// A structure to represent a file's path and line changes.
 #[derive(Debug)]
 pub struct FileObj {
@@ -49,10 +54,38 @@ impl FileObj {
         }
     }
 
+    /// Instantiate an object with file name and the given `ranges` of lines that
+    /// should be analyzed.
+    ///
+    /// The `ranges` are sorted and merged (without expanding them into individual
+    /// line numbers) and then used as both the [`FileObj::added_ranges`] and the
+    /// [`FileObj::diff_chunks`]. The [`FileObj::added_lines`] are left empty.
+    pub fn from_ranges(name: PathBuf, mut ranges: Vec<RangeInclusive<u32>>) -> Self {
+        ranges.retain(|r| !r.is_empty());
+        ranges.sort_unstable_by_key(|r| (*r.start(), *r.end()));
+        let mut merged: Vec<RangeInclusive<u32>> = Vec::with_capacity(ranges.len());
+        for range in ranges {
+            match merged.last_mut() {
+                Some(last) if *range.start() <= last.end().saturating_add(1) => {
+                    if range.end() > last.end() {
+                        *last = *last.start()..=*range.end();
+                    }
+                }
+                _ => merged.push(range),
+            }
+        }
+        FileObj {
+            name,
+            added_lines: Vec::new(),
+            added_ranges: merged.clone(),
+            diff_chunks: merged,
+        }
+    }
+
     /// A helper function to consolidate a [Vec<u32>] of line numbers into a
     /// [Vec<RangeInclusive<u32>>] in which each range describes the beginning and
     /// ending of a group of consecutive line numbers.
-    fn consolidate_numbers_to_ranges(lines: &Vec<u32>) -> Vec<RangeInclusive<u32>> {
+    fn consolidate_numbers_to_ranges(lines: &[u32]) -> Vec<RangeInclusive<u32>> {
         let mut range_start = None;
         let mut ranges: Vec<RangeInclusive<u32>> = Vec::new();
         for (index, number) in lines.iter().enumerate() {
@@ -80,33 +113,109 @@ impl FileObj {
     }
 }
 
-/// Describes if a specified `file_name` is contained within the given `set` of paths.
+/// Describes if the given `file_name` or any of its parent directories satisfies the
+/// given `is_match` predicate.
 ///
-/// The `set` of paths is used as domains, so the specified `file_name` can be a direct
-/// or distant descendant of any given paths in the `set`.
-pub fn is_file_in_list(file_name: &Path, set: &[String], prompt: String) -> bool {
-    for pattern in set {
-        let pat = Path::new(pattern);
-        if pat.is_file() {
-            if file_name == pat {
-                log::debug!(
-                    "{} is {prompt} as specified via {:?}",
-                    file_name.to_string_lossy().replace('\\', "/"),
-                    pat
-                );
-                return true;
-            }
-        } else if pat.is_dir() && file_name.starts_with(pat) {
-            log::debug!(
-                "{} is {prompt} as specified in domain {:?}",
-                file_name.to_string_lossy().replace('\\', "/"),
-                pat
-            );
+/// The `file_name` is converted to a POSIX path (without a leading `./`) before it is
+/// passed to `is_match`. This is useful for matching glob patterns that describe a
+/// directory (eg. `legacy` or `build*`) against the files within that directory.
+pub fn is_path_or_parent_match<F>(file_name: &Path, is_match: F) -> bool
+where
+    F: Fn(&Path) -> bool,
+{
+    let posix = file_name.to_string_lossy().replace('\\', "/");
+    let mut path = Path::new(posix.trim_start_matches("./"));
+    loop {
+        if is_match(path) {
             return true;
         }
-        // else file doesn't exist; return false
+        match path.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => path = parent,
+            _ => return false,
+        }
     }
-    false
+}
+
+/// A set of paths (as given to the `--ignore` option) that is compiled once for
+/// matching against many files.
+///
+/// Each path is treated as a glob pattern relative to the repository root.
+#[derive(Debug, Clone, Default)]
+pub struct PathSet {
+    /// The paths as they were given.
+    patterns: Vec<String>,
+
+    /// The compiled glob patterns (in the same order as `patterns`).
+    matcher: GlobSet,
+}
+
+impl PathSet {
+    /// Compile the given `patterns` into a set.
+    ///
+    /// A pattern that is not a valid glob (eg. `src/[`) is matched literally, and a
+    /// warning is logged about it.
+    pub fn new(patterns: Vec<String>) -> Self {
+        let mut builder = GlobSetBuilder::new();
+        for pattern in &patterns {
+            let pat = pattern.replace('\\', "/");
+            let mut pat = pat.trim_start_matches("./").trim_end_matches('/');
+            if pat.is_empty() || pat == "." {
+                // the repo root is a domain for all files
+                pat = "**";
+            }
+            let glob = GlobBuilder::new(pat)
+                .literal_separator(true)
+                .build()
+                .or_else(|e| {
+                    log::warn!(
+                        "{pattern:?} is not a valid glob pattern ({e}); matching it literally"
+                    );
+                    GlobBuilder::new(&globset::escape(pat))
+                        .literal_separator(true)
+                        .build()
+                })
+                .expect("an escaped glob pattern should be valid");
+            builder.add(glob);
+        }
+        let matcher = builder.build().unwrap_or_else(|e| {
+            log::error!("Failed to compile the set of glob patterns: {e}");
+            GlobSet::empty()
+        });
+        PathSet { patterns, matcher }
+    }
+
+    /// The paths as they were given.
+    pub fn patterns(&self) -> &[String] {
+        &self.patterns
+    }
+
+    /// Is this set empty?
+    pub fn is_empty(&self) -> bool {
+        self.patterns.is_empty()
+    }
+}
+
+/// Describes if a specified `file_name` is contained within the given `set` of paths.
+///
+/// The `set` of paths is also used as domains, so the specified `file_name` can be a
+/// direct or distant descendant of any given paths in the `set`.
+///
+/// This does not require the `file_name` (or the paths in the `set`) to exist on the
+/// file system.
+pub fn is_file_in_list(file_name: &Path, set: &PathSet, prompt: String) -> bool {
+    let matched = Cell::new(None);
+    let is_match = is_path_or_parent_match(file_name, |path| {
+        matched.set(set.matcher.matches(path).first().copied());
+        matched.get().is_some()
+    });
+    if is_match {
+        log::debug!(
+            "{} is {prompt} as specified via {:?}",
+            file_name.to_string_lossy().replace('\\', "/"),
+            set.patterns[matched.get().unwrap()]
+        );
+    }
+    is_match
 }
 
 /// A helper function that checks if `entry` satisfies the following conditions (in
@@ -120,8 +229,8 @@ pub fn is_file_in_list(file_name: &Path, set: &[String], prompt: String) -> bool
 pub fn is_source_or_ignored(
     entry: &Path,
     extensions: &[&str],
-    ignored: &[String],
-    not_ignored: &[String],
+    ignored: &PathSet,
+    not_ignored: &PathSet,
 ) -> bool {
     let extension = entry.extension();
     if extension.is_none() {
@@ -148,16 +257,36 @@ pub fn is_source_or_ignored(
     false
 }
 
+/// Describes if the given `entry` is ignored by the `.gitignore` rules of the given
+/// `git_repo`.
+///
+/// If no `git_repo` is given, then this always returns `false`.
+fn is_git_ignored(entry: &Path, git_repo: Option<&Repository>) -> bool {
+    if let Some(repo) = git_repo {
+        let rel_path = entry.strip_prefix("./").unwrap_or(entry);
+        if repo.is_path_ignored(rel_path).unwrap_or(false) {
+            log::debug!(
+                "{} is ignored by .gitignore rules",
+                rel_path.to_string_lossy().replace('\\', "/")
+            );
+            return true;
+        }
+    }
+    false
+}
+
 /// Walks a given `root_path` recursively and returns a [`Vec<FileObj>`] that
 ///
 /// - uses at least 1 of the `extensions`
 /// - is not specified in the given list of `ignored` paths
 /// - is specified in the given list `not_ignored` paths (which supersedes `ignored` paths)
+/// - is not ignored by the `.gitignore` rules of the given `git_repo` (if any)
 pub fn list_source_files(
     extensions: &[&str],
-    ignored: &[String],
-    not_ignored: &[String],
+    ignored: &PathSet,
+    not_ignored: &PathSet,
     root_path: &str,
+    git_repo: Option<&Repository>,
 ) -> Vec<FileObj> {
     let mut files: Vec<FileObj> = Vec::new();
     let entries = fs::read_dir(root_path)
@@ -166,9 +295,12 @@ pub fn list_source_files(
         .collect::<Result<Vec<_>, io::Error>>()
         .unwrap();
     for entry in entries {
+        if is_git_ignored(&entry, git_repo) {
+            continue;
+        }
         if entry.is_dir() {
             let mut is_hidden = false;
-            let parent = entry.components().last().expect("parent not known");
+            let parent = entry.components().next_back().expect("parent not known");
             if parent.as_os_str().to_str().unwrap().starts_with('.') {
                 is_hidden = true;
             }
@@ -178,13 +310,14 @@ pub fn list_source_files(
                     ignored,
                     not_ignored,
                     &entry.into_os_string().into_string().unwrap(),
+                    git_repo,
                 ));
             }
         } else {
             let is_valid_src = is_source_or_ignored(&entry, extensions, ignored, not_ignored);
             if is_valid_src {
                 files.push(FileObj::new(
-                    entry.clone().strip_prefix("./").unwrap().to_path_buf(),
+                    entry.strip_prefix("./").unwrap_or(&entry).to_path_buf(),
                 ));
             }
         }
@@ -206,9 +339,9 @@ pub fn get_line_cols_from_offset(file_path: &PathBuf, offset: usize) -> (usize,
         .unwrap()
         .read_exact(&mut file_buf)
         .unwrap();
-    let lines = file_buf.split(|byte| byte == &b'\n');
+    let mut lines = file_buf.split(|byte| byte == &b'\n');
     let line_count = lines.clone().count();
-    let column_count = lines.last().unwrap_or(&[]).len() + 1; // +1 because not a 0 based count
+    let column_count = lines.next_back().unwrap_or(&[]).len() + 1; // +1 because not a 0 based count
     (line_count, column_count)
 }
 
@@ -250,7 +383,7 @@ mod test {
 
     use super::{get_line_cols_from_offset, list_source_files, normalize_path, FileObj};
     use crate::cli::{get_arg_parser, parse_ignore};
-    use crate::common_fs::is_file_in_list;
+    use crate::common_fs::{is_file_in_list, is_source_or_ignored, PathSet};
 
     // *********************** tests for normalized paths
 
@@ -289,7 +422,7 @@ mod test {
 
     // ************* tests for ignored paths
 
-    fn setup_ignore(input: &str) -> (Vec<String>, Vec<String>) {
+    fn setup_ignore(input: &str) -> (PathSet, PathSet) {
         let arg_parser = get_arg_parser();
         let args = arg_parser.get_matches_from(vec!["cpp-linter", "-i", input]);
         let ignore_arg = args
@@ -353,30 +486,106 @@ mod test {
         set_current_dir("tests/ignored_paths").unwrap();
         let (ignored, not_ignored) = setup_ignore("!pybind11");
 
-        // using Vec::contains() because these files don't actually exist in project files
+        // these files don't actually exist in project files
         for ignored_submodule in ["./RF24", "./RF24Network", "./RF24Mesh"] {
-            assert!(ignored.contains(&ignored_submodule.to_string()));
-            assert!(!is_file_in_list(
+            assert!(ignored.patterns().contains(&ignored_submodule.to_string()));
+            assert!(is_file_in_list(
                 &PathBuf::from(ignored_submodule.to_string() + "/some_src.cpp"),
                 &ignored,
                 "ignored".to_string()
             ));
         }
-        assert!(not_ignored.contains(&"./pybind11".to_string()));
-        assert!(!is_file_in_list(
+        assert!(not_ignored.patterns().contains(&"./pybind11".to_string()));
+        assert!(is_file_in_list(
             &PathBuf::from("./pybind11/some_src.cpp"),
             &not_ignored,
             "not ignored".to_string()
         ));
     }
 
+    #[test]
+    fn ignore_globs() {
+        let (ignored, not_ignored) =
+            setup_ignore("**/generated/*.pb.h|src/**/test_*.cpp|!src/keep/test_*.cpp");
+        for file in [
+            "generated/msg.pb.h",
+            "./lib/generated/msg.pb.h",
+            "src/test_a.cpp",
+            "src/a/b/test_c.cpp",
+        ] {
+            assert!(is_file_in_list(
+                &PathBuf::from(file),
+                &ignored,
+                "ignored".to_string()
+            ));
+        }
+        for file in [
+            "lib/generated/nested/msg.pb.h",
+            "lib/generated/msg.h",
+            "src/a/test.cpp",
+        ] {
+            assert!(!is_file_in_list(
+                &PathBuf::from(file),
+                &ignored,
+                "ignored".to_string()
+            ));
+        }
+        assert!(is_file_in_list(
+            &PathBuf::from("src/keep/test_b.cpp"),
+            &not_ignored,
+            "not ignored".to_string()
+        ));
+        assert!(is_source_or_ignored(
+            &PathBuf::from("src/keep/test_b.cpp"),
+            &["cpp"],
+            &ignored,
+            &not_ignored
+        ));
+        assert!(!is_source_or_ignored(
+            &PathBuf::from("src/other/test_b.cpp"),
+            &["cpp"],
+            &ignored,
+            &not_ignored
+        ));
+    }
+
+    #[test]
+    fn ignore_glob_dir() {
+        let (ignored, _) = setup_ignore("build*");
+        assert!(is_file_in_list(
+            &PathBuf::from("build-release/main.cpp"),
+            &ignored,
+            "ignored".to_string()
+        ));
+        assert!(!is_file_in_list(
+            &PathBuf::from("src/build.cpp"),
+            &ignored,
+            "ignored".to_string()
+        ));
+    }
+
+    #[test]
+    fn ignore_invalid_glob() {
+        let (ignored, _) = setup_ignore("src/[");
+        assert!(is_file_in_list(
+            &PathBuf::from("src/[/main.cpp"),
+            &ignored,
+            "ignored".to_string()
+        ));
+        assert!(!is_file_in_list(
+            &PathBuf::from("src/main.cpp"),
+            &ignored,
+            "ignored".to_string()
+        ));
+    }
+
     // *********************** tests for recursive path search
 
     #[test]
     fn walk_dir_recursively() {
         let (ignored, not_ignored) = setup_ignore("target");
         let extensions = vec!["cpp", "hpp"];
-        let files = list_source_files(&extensions, &ignored, &not_ignored, ".");
+        let files = list_source_files(&extensions, &ignored, &not_ignored, ".", None);
         assert!(!files.is_empty());
         for file in files {
             assert!(extensions.contains(
@@ -435,4 +644,25 @@ mod test {
         let ranges = file_obj.get_ranges(1);
         assert_eq!(ranges, vec![4..=5, 9..=9]);
     }
+
+    #[test]
+    fn from_ranges_merged() {
+        let file_obj = FileObj::from_ranges(
+            PathBuf::from("tests/demo/demo.cpp"),
+            vec![30..=31, 10..=20, 15..=25, 26..=27, 42..=42, 1..=40],
+        );
+        assert!(file_obj.added_lines.is_empty());
+        assert_eq!(file_obj.added_ranges, vec![1..=40, 42..=42]);
+        assert_eq!(file_obj.diff_chunks, file_obj.added_ranges);
+    }
+
+    #[test]
+    fn from_ranges_huge() {
+        let file_obj = FileObj::from_ranges(
+            PathBuf::from("tests/demo/demo.cpp"),
+            vec![u32::MAX - 1..=u32::MAX, 1..=u32::MAX - 2],
+        );
+        assert_eq!(file_obj.get_ranges(1), vec![1..=u32::MAX]);
+        assert_eq!(file_obj.get_ranges(2), vec![1..=u32::MAX]);
+    }
 }
@@ -0,0 +1,246 @@
+//! This module holds functionality related to the optional configuration file.
+//!
+//! The configuration file uses TOML syntax. Currently, it is only used to override
+//! some of the CLI options for files that match a set of glob patterns:
+//!
+//! ```toml
+//! [[overrides]]
+//! paths = ["legacy/**", "third_party/**"]
+//! style = "file"
+//! tidy-checks = "-*,bugprone-*"
+//! lines-changed-only = false
+//! extra-args = ["-std=c++11"]
+//! ```
+//!
+//! Overrides are applied in order of appearance, so a later `[[overrides]]` section
+//! supersedes the values of an earlier one for files that match both.
+
+use std::fs;
+use std::path::Path;
+
+// non-std crates
+use globset::{GlobBuilder, GlobSet, GlobSetBuilder};
+use serde::{Deserialize, Deserializer};
+
+// project specific modules/crates
+use crate::clang_tools::ClangParams;
+use crate::common_fs::is_path_or_parent_match;
+
+/// A structure to represent the deserialized configuration file.
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    /// A list of [`ConfigOverride`]s (in order of appearance).
+    #[serde(default)]
+    pub overrides: Vec<ConfigOverride>,
+}
+
+/// A section of the configuration file that overrides CLI options for specific paths.
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct ConfigOverride {
+    /// A list of glob patterns that describe paths relative to the repository root.
+    ///
+    /// If a pattern matches a directory, then all files in that directory are matched.
+    pub paths: Vec<String>,
+
+    /// Overrides the `--style` option.
+    pub style: Option<String>,
+
+    /// Overrides the `--tidy-checks` option.
+    pub tidy_checks: Option<String>,
+
+    /// Overrides the `--lines-changed-only` option.
+    ///
+    /// This can be a boolean or the string `"diff"`.
+    #[serde(default, deserialize_with = "deserialize_lines_changed_only")]
+    pub lines_changed_only: Option<u8>,
+
+    /// Overrides the `--extra-arg` option.
+    pub extra_args: Option<Vec<String>>,
+
+    /// The compiled glob patterns from [`ConfigOverride::paths`].
+    #[serde(skip)]
+    matcher: GlobSet,
+}
+
+/// A helper function to deserialize the `lines-changed-only` value from either a
+/// boolean or a string.
+fn deserialize_lines_changed_only<'de, D>(deserializer: D) -> Result<Option<u8>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum BoolOrStr {
+        Bool(bool),
+        Str(String),
+    }
+    match BoolOrStr::deserialize(deserializer)? {
+        BoolOrStr::Bool(val) => Ok(Some(val as u8)),
+        BoolOrStr::Str(val) => match val.as_str() {
+            "false" => Ok(Some(0)),
+            "true" => Ok(Some(1)),
+            "diff" => Ok(Some(2)),
+            _ => Err(serde::de::Error::custom(format!(
+                "invalid lines-changed-only value {val:?}; expected true, false, or \"diff\""
+            ))),
+        },
+    }
+}
+
+impl ConfigOverride {
+    /// Describes if the given `file_name` is matched by any of the
+    /// [`ConfigOverride::paths`].
+    ///
+    /// The `file_name` is expected to be relative to the repository root.
+    pub fn is_match(&self, file_name: &Path) -> bool {
+        is_path_or_parent_match(file_name, |path| self.matcher.is_match(path))
+    }
+}
+
+impl Config {
+    /// Parse the given `content` (in TOML syntax) into a [`Config`] object.
+    pub fn from_toml(content: &str) -> Result<Self, String> {
+        let mut config = toml::from_str::<Config>(content).map_err(|e| e.to_string())?;
+        for overrides in &mut config.overrides {
+            let mut builder = GlobSetBuilder::new();
+            for pattern in &overrides.paths {
+                let pat = pattern.replace('\\', "/");
+                let glob = GlobBuilder::new(pat.trim_start_matches("./").trim_end_matches('/'))
+                    .literal_separator(true)
+                    .build()
+                    .map_err(|e| e.to_string())?;
+                builder.add(glob);
+            }
+            overrides.matcher = builder.build().map_err(|e| e.to_string())?;
+        }
+        Ok(config)
+    }
+
+    /// Read the configuration file at the given `path`.
+    ///
+    /// If the file does not exist, then a default [`Config`] is returned (which
+    /// overrides nothing).
+    pub fn from_file(path: &Path) -> Result<Self, String> {
+        if !path.is_file() {
+            log::debug!("No configuration file found at {:?}", path);
+            return Ok(Config::default());
+        }
+        let content = fs::read_to_string(path).map_err(|e| e.to_string())?;
+        let config = Config::from_toml(&content)?;
+        log::info!(
+            "Using configuration file {:?} with {} override(s)",
+            path,
+            config.overrides.len()
+        );
+        Ok(config)
+    }
+
+    /// Get the [`ClangParams`] applicable to the given `file_name`.
+    ///
+    /// All [`ConfigOverride`]s that match the `file_name` are applied (in order) to a
+    /// copy of the given `base` parameters.
+    pub fn get_params(&self, file_name: &Path, base: &ClangParams) -> ClangParams {
+        let mut params = base.clone();
+        for (index, overrides) in self.overrides.iter().enumerate() {
+            if !overrides.is_match(file_name) {
+                continue;
+            }
+            log::debug!(
+                "Applying override {index} to {}",
+                file_name.to_string_lossy().replace('\\', "/")
+            );
+            if let Some(style) = &overrides.style {
+                params.style = style.clone();
+            }
+            if let Some(tidy_checks) = &overrides.tidy_checks {
+                params.tidy_checks = tidy_checks.clone();
+            }
+            if let Some(lines_changed_only) = overrides.lines_changed_only {
+                params.lines_changed_only = lines_changed_only;
+            }
+            if let Some(extra_args) = &overrides.extra_args {
+                params.extra_args = Some(extra_args.clone());
+            }
+        }
+        params
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::PathBuf;
+
+    use super::Config;
+    use crate::clang_tools::ClangParams;
+
+    const CONFIG: &str = r#"
+[[overrides]]
+paths = ["legacy/**"]
+style = "file"
+tidy-checks = "-*"
+lines-changed-only = false
+
+[[overrides]]
+paths = ["legacy/new", "src/**/test_*.cpp"]
+tidy-checks = "bugprone-*"
+lines-changed-only = "diff"
+extra-args = ["-std=c++17"]
+"#;
+
+    fn base_params() -> ClangParams {
+        ClangParams {
+            tidy_checks: String::from("readability-*"),
+            style: String::from("llvm"),
+            lines_changed_only: 1,
+            extra_args: None,
+            header_filter: None,
+        }
+    }
+
+    #[test]
+    fn no_match() {
+        let config = Config::from_toml(CONFIG).unwrap();
+        let base = base_params();
+        let params = config.get_params(&PathBuf::from("src/main.cpp"), &base);
+        assert_eq!(params, base);
+    }
+
+    #[test]
+    fn single_match() {
+        let config = Config::from_toml(CONFIG).unwrap();
+        let params = config.get_params(&PathBuf::from("legacy/old/code.c"), &base_params());
+        assert_eq!(params.style, "file");
+        assert_eq!(params.tidy_checks, "-*");
+        assert_eq!(params.lines_changed_only, 0);
+        assert!(params.extra_args.is_none());
+    }
+
+    #[test]
+    fn ordered_matches() {
+        let config = Config::from_toml(CONFIG).unwrap();
+        let params = config.get_params(&PathBuf::from("./legacy/new/code.cpp"), &base_params());
+        assert_eq!(params.style, "file");
+        assert_eq!(params.tidy_checks, "bugprone-*");
+        assert_eq!(params.lines_changed_only, 2);
+        assert_eq!(params.extra_args, Some(vec![String::from("-std=c++17")]));
+
+        let params = config.get_params(&PathBuf::from("src/a/b/test_c.cpp"), &base_params());
+        assert_eq!(params.tidy_checks, "bugprone-*");
+        assert_eq!(params.style, "llvm");
+    }
+
+    #[test]
+    fn bad_lines_changed_only() {
+        let config =
+            Config::from_toml("[[overrides]]\npaths = [\"*\"]\nlines-changed-only = \"yes\"");
+        assert!(config.is_err());
+    }
+
+    #[test]
+    fn missing_file() {
+        let config = Config::from_file(&PathBuf::from("not-a-file.toml")).unwrap();
+        assert!(config.overrides.is_empty());
+    }
+}
@@ -1,7 +1,8 @@
 //! This module is primarily used to parse diff blobs.
 //!
 //! It can also be used (locally) to get a list of files changes from either the last
-//! commit or the next commit's staging area.
+//! commit, the next commit's staging area, the working directory, or the merge-base of
+//! two refs.
 //!
 //! This also includes a private module that is used as a fallback (brute force)
 //! mechanism when parsing diffs fail using libgit2. NOTE: parsing a diff from a buffer
@@ -11,10 +12,10 @@
 use std::{ops::RangeInclusive, path::PathBuf};
 
 // non-std crates
-use git2::{Diff, Error, Patch, Repository};
+use git2::{Diff, DiffOptions, Error, Patch, Repository};
 
 // project specific modules/crates
-use crate::common_fs::{is_source_or_ignored, FileObj};
+use crate::common_fs::{is_source_or_ignored, FileObj, PathSet};
 
 /// This (re-)initializes the repository located in the specified `path`.
 ///
@@ -25,6 +26,46 @@ pub fn open_repo(path: &str) -> Result<Repository, Error> {
     Repository::open(PathBuf::from(path).as_path())
 }
 
+/// Lists the source files that are tracked in the index of the given
+/// [`git2::Repository`].
+///
+/// This is a faster alternative to [`list_source_files()`](crate::common_fs::list_source_files)
+/// because untracked files (eg. build artifacts) are never visited. The resulting list
+/// is filtered by the specified `extensions`, `ignored` and `not_ignored` paths.
+/// Submodules, files in hidden directories, and tracked files that were removed from
+/// the working tree are skipped. The listed paths are relative to the repository's
+/// working tree (regardless of the current working directory).
+pub fn list_tracked_files(
+    repo: &Repository,
+    extensions: &[&str],
+    ignored: &PathSet,
+    not_ignored: &PathSet,
+) -> Vec<FileObj> {
+    let mut files: Vec<FileObj> = Vec::new();
+    let index = repo.index().expect("Could not read the repository's index");
+    for entry in index.iter() {
+        if entry.mode & 0o170000 == 0o160000 {
+            // entry is a submodule
+            continue;
+        }
+        let file_path = PathBuf::from(String::from_utf8_lossy(&entry.path).to_string());
+        let is_hidden = file_path
+            .parent()
+            .is_some_and(|dir| dir.iter().any(|c| c.to_string_lossy().starts_with('.')));
+        let is_in_workdir = match repo.workdir() {
+            Some(workdir) => workdir.join(&file_path).is_file(),
+            None => false,
+        };
+        if is_hidden || !is_in_workdir {
+            continue;
+        }
+        if is_source_or_ignored(&file_path, extensions, ignored, not_ignored) {
+            files.push(FileObj::new(file_path));
+        }
+    }
+    files
+}
+
 /// Fetches the SHA1 of the commit for the specified [`git2::Repository`].
 ///
 /// The optionally specified `depth` can be used to traverse the tree a number of times
@@ -45,7 +86,7 @@ fn get_sha(repo: &Repository, depth: Option<u32>) -> Result<git2::Object<'_>, Er
 /// If there are files staged for a commit, then the resulting [`Diff`] will describe
 /// the staged changes. However, if there are no staged changes, then the last commit's
 /// [`Diff`] is returned.
-pub fn get_diff(repo: &Repository) -> git2::Diff {
+pub fn get_diff(repo: &Repository) -> git2::Diff<'_> {
     let head = get_sha(repo, None).unwrap().peel_to_tree().unwrap();
     let mut has_staged_files = false;
     for entry in repo.statuses(None).unwrap().iter() {
@@ -72,6 +113,55 @@ pub fn get_diff(repo: &Repository) -> git2::Diff {
     }
 }
 
+/// Fetch the [`git2::Diff`] between the `head` ref and its merge-base with the `base` ref.
+///
+/// This is equivalent to `git diff base...head` and describes the changes that a pull
+/// request from `head` into `base` would introduce. If `head` is not specified, then
+/// `"HEAD"` is used.
+///
+/// The `base` and `head` refs can be anything that git can resolve to a commit (eg. a
+/// branch name, a tag, or a SHA).
+pub fn get_diff_from_refs<'a>(
+    repo: &'a Repository,
+    base: &str,
+    head: Option<&str>,
+) -> Result<git2::Diff<'a>, Error> {
+    let head_commit = repo
+        .revparse_single(head.unwrap_or("HEAD"))?
+        .peel_to_commit()?;
+    let base_commit = repo.revparse_single(base)?.peel_to_commit()?;
+    let merge_base = repo.merge_base(base_commit.id(), head_commit.id())?;
+    log::info!(
+        "Getting diff from merge-base {} of {base} and {}",
+        merge_base,
+        head.unwrap_or("HEAD")
+    );
+    let base_tree = repo.find_commit(merge_base)?.tree()?;
+    repo.diff_tree_to_tree(Some(&base_tree), Some(&head_commit.tree()?), None)
+}
+
+/// Fetch the [`git2::Diff`] of the changes in the working directory of the given
+/// [`git2::Repository`].
+///
+/// If `from_index` is `true`, then the working directory is compared to the index (only
+/// unstaged changes). Otherwise, the working directory is compared to `"HEAD"` (both
+/// staged and unstaged changes).
+///
+/// Untracked files (that are not ignored by `.gitignore` rules) are included in the
+/// resulting [`Diff`] as new files.
+pub fn get_diff_to_workdir(repo: &Repository, from_index: bool) -> Result<git2::Diff<'_>, Error> {
+    let mut opts = DiffOptions::new();
+    opts.include_untracked(true)
+        .recurse_untracked_dirs(true)
+        .show_untracked_content(true);
+    if from_index {
+        repo.diff_index_to_workdir(None, Some(&mut opts))
+    } else {
+        let head = get_sha(repo, None)?.peel_to_tree()?;
+        repo.diff_tree_to_workdir_with_index(Some(&head), Some(&mut opts))
+    }
+}
+
 /// Parses a patch for a single file in a diff.
 ///
 /// Returns the list of line numbers that have additions and the ranges spanning each
@@ -102,8 +192,8 @@ fn parse_patch(patch: &Patch) -> (Vec<u32>, Vec<RangeInclusive<u32>>) {
 pub fn parse_diff(
     diff: &git2::Diff,
     extensions: &[&str],
-    ignored: &[String],
-    not_ignored: &[String],
+    ignored: &PathSet,
+    not_ignored: &PathSet,
 ) -> Vec<FileObj> {
     let mut files: Vec<FileObj> = Vec::new();
     for file_idx in 0..diff.deltas().count() {
@@ -113,6 +203,7 @@ pub fn parse_diff(
             git2::Delta::Added,
             git2::Delta::Modified,
             git2::Delta::Renamed,
+            git2::Delta::Untracked,
         ]
         .contains(&diff_delta.status())
             && is_source_or_ignored(&file_path, extensions, ignored, not_ignored)
@@ -135,8 +226,8 @@ pub fn parse_diff(
 pub fn parse_diff_from_buf(
     buff: &[u8],
     extensions: &[&str],
-    ignored: &[String],
-    not_ignored: &[String],
+    ignored: &PathSet,
+    not_ignored: &PathSet,
 ) -> Vec<FileObj> {
     if let Ok(diff_obj) = &Diff::from_buffer(buff) {
         parse_diff(diff_obj, extensions, ignored, not_ignored)
@@ -163,7 +254,7 @@ mod brute_force_parse_diff {
     use regex::Regex;
     use std::{ops::RangeInclusive, path::PathBuf};
 
-    use crate::common_fs::{is_source_or_ignored, FileObj};
+    use crate::common_fs::{is_source_or_ignored, FileObj, PathSet};
 
     fn get_filename_from_front_matter(front_matter: &str) -> Option<&str> {
         let diff_file_name = Regex::new(r"(?m)^\+\+\+\sb?/(.*)$").unwrap();
@@ -229,8 +320,8 @@ mod brute_force_parse_diff {
     pub fn parse_diff(
         diff: &str,
         extensions: &[&str],
-        ignored: &[String],
-        not_ignored: &[String],
+        ignored: &PathSet,
+        not_ignored: &PathSet,
     ) -> Vec<FileObj> {
         log::error!("Using brute force diff parsing!");
         let mut results = Vec::new();
@@ -265,7 +356,11 @@ mod brute_force_parse_diff {
     mod test {
 
         use super::parse_diff;
-        use crate::{common_fs::FileObj, git::parse_diff_from_buf, logger};
+        use crate::{
+            common_fs::{FileObj, PathSet},
+            git::parse_diff_from_buf,
+            logger,
+        };
 
         static RENAMED_DIFF: &str = r"diff --git a/tests/demo/some source.cpp b/tests/demo/some source.cpp
 similarity index 100%
@@ -283,14 +378,24 @@ rename to /tests/demo/some source.cpp
         #[test]
         fn parse_renamed_diff() {
             let diff_buf = RENAMED_DIFF.as_bytes();
-            let files = parse_diff_from_buf(diff_buf, &[&String::from("cpp")], &[], &[]);
+            let files = parse_diff_from_buf(
+                diff_buf,
+                &[&String::from("cpp")],
+                &PathSet::default(),
+                &PathSet::default(),
+            );
             assert!(files.is_empty());
         }
 
         #[test]
         fn parse_renamed_diff_with_patch() {
             let diff_buf = RENAMED_DIFF_WITH_CHANGES.as_bytes();
-            let files = parse_diff_from_buf(diff_buf, &[&String::from("cpp")], &[], &[]);
+            let files = parse_diff_from_buf(
+                diff_buf,
+                &[&String::from("cpp")],
+                &PathSet::default(),
+                &PathSet::default(),
+            );
             assert!(!files.is_empty());
         }
 
@@ -299,12 +404,17 @@ rename to /tests/demo/some source.cpp
         fn setup_parsed(buf: &str, extensions: &[&str]) -> (Vec<FileObj>, Vec<FileObj>) {
             logger::init().unwrap_or_default();
             (
-                parse_diff_from_buf(buf.as_bytes(), extensions, &[], &[]),
-                parse_diff(buf, extensions, &[], &[]),
+                parse_diff_from_buf(
+                    buf.as_bytes(),
+                    extensions,
+                    &PathSet::default(),
+                    &PathSet::default(),
+                ),
+                parse_diff(buf, extensions, &PathSet::default(), &PathSet::default()),
             )
         }
 
-        fn assert_files_eq(files_from_a: &Vec<FileObj>, files_from_b: &Vec<FileObj>) {
+        fn assert_files_eq(files_from_a: &[FileObj], files_from_b: &[FileObj]) {
             assert_eq!(files_from_a.len(), files_from_b.len());
             for (a, b) in files_from_a.iter().zip(files_from_b) {
                 assert_eq!(a.name, b.name);
@@ -345,7 +455,7 @@ rename to /tests/demo/some source.cpp
 mod test {
     use std::{
         env::{self, current_dir, set_current_dir},
-        fs::read,
+        fs::{create_dir_all, read, write},
     };
 
     use git2::build::CheckoutBuilder;
@@ -374,7 +484,13 @@ mod test {
 
     use tempfile::{tempdir, TempDir};
 
-    use crate::{cli::parse_ignore, github_api::GithubApiClient, rest_api::RestApiClient};
+    use super::{get_diff_from_refs, get_diff_to_workdir, list_tracked_files, parse_diff};
+    use crate::{
+        cli::parse_ignore,
+        common_fs::{list_source_files, FileObj, PathSet},
+        github_api::GithubApiClient,
+        rest_api::RestApiClient,
+    };
 
     fn get_temp_dir() -> TempDir {
         let tmp = tempdir().unwrap();
@@ -470,4 +586,175 @@ mod test {
         set_current_dir(cur_dir).unwrap(); // prep to delete temp_folder
         drop(tmp); // delete temp_folder
     }
+
+    /// Creates a local repository with tracked, untracked, and ignored sources.
+    fn init_local_repo(tmp: &TempDir) -> Repository {
+        let repo = Repository::init(tmp.path()).unwrap();
+        for (name, content) in [
+            (".gitignore", "build/\n*.pb.h\n"),
+            ("src/tracked.cpp", "int main() { return 0; }\n"),
+            ("src/untracked.cpp", "int main() { return 0; }\n"),
+            ("src/msg.pb.h", "// generated\n"),
+            ("build/generated.cpp", "// generated\n"),
+        ] {
+            let path = tmp.path().join(name);
+            create_dir_all(path.parent().unwrap()).unwrap();
+            write(path, content).unwrap();
+        }
+        let mut index = repo.index().unwrap();
+        index
+            .add_all(
+                [".gitignore", "src/tracked.cpp"],
+                IndexAddOption::DEFAULT,
+                None,
+            )
+            .unwrap();
+        index.write().unwrap();
+        repo
+    }
+
+    fn file_names(files: &[FileObj]) -> Vec<String> {
+        let mut names = files
+            .iter()
+            .map(|f| f.name.to_string_lossy().replace('\\', "/"))
+            .collect::<Vec<_>>();
+        names.sort();
+        names
+    }
+
+    #[test]
+    fn list_tracked_sources() {
+        let tmp = get_temp_dir();
+        let repo = init_local_repo(&tmp);
+        // the current working directory is not the repository's working tree
+        let files = list_tracked_files(
+            &repo,
+            &["cpp", "h"],
+            &PathSet::default(),
+            &PathSet::default(),
+        );
+        assert_eq!(file_names(&files), vec!["src/tracked.cpp"]);
+    }
+
+    #[test]
+    fn list_sources_respecting_gitignore() {
+        let tmp = get_temp_dir();
+        let repo = init_local_repo(&tmp);
+        let root_path = tmp.path().to_string_lossy().to_string();
+        let all_files = list_source_files(
+            &["cpp", "h"],
+            &PathSet::default(),
+            &PathSet::default(),
+            &root_path,
+            None,
+        );
+        let files = list_source_files(
+            &["cpp", "h"],
+            &PathSet::default(),
+            &PathSet::default(),
+            &root_path,
+            Some(&repo),
+        );
+        assert_eq!(all_files.len(), 4);
+        let files = files
+            .into_iter()
+            .map(|file| FileObj::new(file.name.strip_prefix(tmp.path()).unwrap().to_path_buf()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            file_names(&files),
+            vec!["src/tracked.cpp", "src/untracked.cpp"]
+        );
+    }
+
+    /// Writes the given `files` and commits them (on top of the given `parent`) to the
+    /// specified `branch`.
+    fn commit_files(
+        repo: &Repository,
+        branch: &str,
+        parent: Option<&git2::Commit>,
+        files: &[(&str, &str)],
+    ) -> git2::Oid {
+        let workdir = repo.workdir().unwrap().to_path_buf();
+        let mut index = repo.index().unwrap();
+        if let Some(parent) = parent {
+            index.read_tree(&parent.tree().unwrap()).unwrap();
+        }
+        for (name, content) in files {
+            write(workdir.join(name), content).unwrap();
+            index.add_path(std::path::Path::new(name)).unwrap();
+        }
+        index.write().unwrap();
+        let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+        let sig = git2::Signature::now("cpp-linter", "cpp-linter@example.com").unwrap();
+        let parents = parent.into_iter().collect::<Vec<_>>();
+        repo.commit(
+            Some(&format!("refs/heads/{branch}")),
+            &sig,
+            &sig,
+            "test commit",
+            &tree,
+            &parents,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn diff_from_merge_base() {
+        let tmp = get_temp_dir();
+        let repo = Repository::init(tmp.path()).unwrap();
+        let root = commit_files(&repo, "base", None, &[("a.cpp", "int a;\n")]);
+        let root = repo.find_commit(root).unwrap();
+        // advance the base branch after the feature branch was created
+        commit_files(&repo, "base", Some(&root), &[("main.cpp", "int m;\n")]);
+        let feature = commit_files(&repo, "feature", Some(&root), &[("f1.cpp", "int f;\n")]);
+        let feature = repo.find_commit(feature).unwrap();
+        commit_files(
+            &repo,
+            "feature",
+            Some(&feature),
+            &[("a.cpp", "int a;\nint b;\n")],
+        );
+
+        let diff = get_diff_from_refs(&repo, "base", Some("feature")).unwrap();
+        let files = parse_diff(&diff, &["cpp"], &PathSet::default(), &PathSet::default());
+        let names = file_names(&files);
+        assert_eq!(names, vec!["a.cpp", "f1.cpp"]);
+        let a_cpp = files.iter().find(|f| f.name.ends_with("a.cpp")).unwrap();
+        assert_eq!(a_cpp.added_lines, vec![2]);
+
+        assert!(get_diff_from_refs(&repo, "not-a-ref", None).is_err());
+    }
+
+    #[test]
+    fn diff_workdir() {
+        let tmp = get_temp_dir();
+        let repo = Repository::init(tmp.path()).unwrap();
+        let head = commit_files(&repo, "main", None, &[("a.cpp", "int a;\n")]);
+        repo.set_head("refs/heads/main").unwrap();
+        repo.checkout_head(Some(CheckoutBuilder::new().force()))
+            .unwrap();
+        let head = repo.find_commit(head).unwrap();
+        // stage a new file
+        write(tmp.path().join("b.cpp"), "int b;\n").unwrap();
+        let mut index = repo.index().unwrap();
+        index.read_tree(&head.tree().unwrap()).unwrap();
+        index.add_path(std::path::Path::new("b.cpp")).unwrap();
+        index.write().unwrap();
+        // make unstaged changes
+        write(tmp.path().join("a.cpp"), "int a;\nint a2;\n").unwrap();
+        create_dir_all(tmp.path().join("new")).unwrap();
+        write(tmp.path().join("new/c.cpp"), "int c;\n").unwrap();
+
+        let diff = get_diff_to_workdir(&repo, false).unwrap();
+        let files = parse_diff(&diff, &["cpp"], &PathSet::default(), &PathSet::default());
+        assert_eq!(file_names(&files), vec!["a.cpp", "b.cpp", "new/c.cpp"]);
+        let c_cpp = files.iter().find(|f| f.name.ends_with("c.cpp")).unwrap();
+        assert_eq!(c_cpp.added_lines, vec![1]);
+
+        let diff = get_diff_to_workdir(&repo, true).unwrap();
+        let files = parse_diff(&diff, &["cpp"], &PathSet::default(), &PathSet::default());
+        assert_eq!(file_names(&files), vec!["a.cpp", "new/c.cpp"]);
+        let a_cpp = files.iter().find(|f| f.name.ends_with("a.cpp")).unwrap();
+        assert_eq!(a_cpp.added_lines, vec![2]);
+    }
 }
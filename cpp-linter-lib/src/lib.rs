@@ -9,9 +9,11 @@
 //! `cpp_linter.cpp_linter` in the python path.
 
 // project specific modules/crates
+pub mod baseline;
 pub mod clang_tools;
 pub mod cli;
 pub mod common_fs;
+pub mod config;
 pub mod git;
 pub mod rest_api;
 pub use rest_api::github_api;
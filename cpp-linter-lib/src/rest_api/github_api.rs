@@ -4,26 +4,33 @@ use std::collections::HashMap;
 use std::env;
 use std::fs::OpenOptions;
 use std::io::{Read, Write};
+use std::path::Path;
 
 // non-std crates
-use reqwest::blocking::Client;
+use reqwest::blocking::{Client, RequestBuilder, Response};
 use reqwest::header::{HeaderMap, HeaderValue};
 use reqwest::Method;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use serde_json;
 
 // project specific modules/crates
-use crate::clang_tools::{clang_format::FormatAdvice, clang_tidy::TidyNotification};
-use crate::common_fs::FileObj;
-use crate::git::{get_diff, open_repo, parse_diff, parse_diff_from_buf};
+use crate::clang_tools::{clang_format::FormatAdvice, clang_tidy::TidyNotification, ToolError};
+use crate::common_fs::{is_source_or_ignored, FileObj, PathSet};
+use crate::git::{get_diff, get_diff_from_refs, open_repo, parse_diff, parse_diff_from_buf};
 
-use super::RestApiClient;
+use super::{
+    get_next_page_url, make_comment_marker, send_api_request, FeedbackInput, RestApiClient,
+    RestApiError, RetryPolicy, TidyCounts, MAX_COMMENT_LEN,
+};
 
 /// A structure to work with Github REST API.
 pub struct GithubApiClient {
     /// The HTTP request client to be used for all REST API calls.
     client: Client,
 
+    /// The policy used to retry failed (or rate limited) REST API calls.
+    retry_policy: RetryPolicy,
+
     /// The CI run's event payload from the webhook that triggered the workflow.
     event_payload: Option<serde_json::Value>,
 
@@ -41,6 +48,13 @@ pub struct GithubApiClient {
 
     /// The value of the `ACTIONS_STEP_DEBUG` environment variable.
     pub debug_enabled: bool,
+
+    /// The value of the `GITHUB_TOKEN` environment variable.
+    token: Option<String>,
+
+    /// The URL of the workflow run (made from the `GITHUB_SERVER_URL`,
+    /// `GITHUB_REPOSITORY`, and `GITHUB_RUN_ID` environment variables).
+    run_url: Option<String>,
 }
 
 impl Default for GithubApiClient {
@@ -53,13 +67,13 @@ impl GithubApiClient {
     pub fn new() -> Self {
         GithubApiClient {
             client: reqwest::blocking::Client::new(),
+            retry_policy: RetryPolicy::default(),
             event_payload: {
-                let event_payload_path = env::var("GITHUB_EVENT_PATH");
-                if event_payload_path.is_ok() {
+                if let Ok(event_payload_path) = env::var("GITHUB_EVENT_PATH") {
                     let file_buf = &mut String::new();
                     OpenOptions::new()
                         .read(true)
-                        .open(event_payload_path.unwrap())
+                        .open(event_payload_path)
                         .unwrap()
                         .read_to_string(file_buf)
                         .unwrap();
@@ -70,20 +84,20 @@ impl GithubApiClient {
             },
             event_name: env::var("GITHUB_EVENT_NAME").unwrap_or(String::from("default")),
             api_url: env::var("GITHUB_API_URL").unwrap_or(String::from("https://api.github.com")),
-            repo: if let Ok(val) = env::var("GITHUB_REPOSITORY") {
-                Some(val)
-            } else {
-                None
-            },
-            sha: if let Ok(val) = env::var("GITHUB_SHA") {
-                Some(val)
-            } else {
-                None
-            },
+            repo: env::var("GITHUB_REPOSITORY").ok(),
+            sha: env::var("GITHUB_SHA").ok(),
             debug_enabled: match env::var("ACTIONS_STEP_DEBUG") {
                 Ok(val) => val == "true",
                 Err(_) => false,
             },
+            token: env::var("GITHUB_TOKEN").ok(),
+            run_url: match (env::var("GITHUB_REPOSITORY"), env::var("GITHUB_RUN_ID")) {
+                (Ok(repo), Ok(run_id)) => Some(format!(
+                    "{}/{repo}/actions/runs/{run_id}",
+                    env::var("GITHUB_SERVER_URL").unwrap_or(String::from("https://github.com"))
+                )),
+                _ => None,
+            },
         }
     }
 }
@@ -95,7 +109,9 @@ impl RestApiClient for GithubApiClient {
         checks_failed: i32,
         format_checks_failed: Option<i32>,
         tidy_checks_failed: Option<i32>,
+        tidy_counts: Option<&TidyCounts>,
     ) -> i32 {
+        let tidy_counts = tidy_counts.cloned().unwrap_or_default();
         if let Ok(gh_out) = env::var("GITHUB_OUTPUT") {
             let mut gh_out_file = OpenOptions::new()
                 .append(true)
@@ -103,10 +119,14 @@ impl RestApiClient for GithubApiClient {
                 .expect("GITHUB_OUTPUT file could not be opened");
             if let Err(e) = writeln!(
                 gh_out_file,
-                "checks-failed={}\nformat-checks-failed={}\ntidy-checks-failed={}",
+                "checks-failed={}\nformat-checks-failed={}\ntidy-checks-failed={}\n\
+                 tidy-errors={}\ntidy-warnings={}\ntidy-notes={}",
                 checks_failed,
                 format_checks_failed.unwrap_or(0),
                 tidy_checks_failed.unwrap_or(0),
+                tidy_counts.errors,
+                tidy_counts.warnings,
+                tidy_counts.notes,
             ) {
                 panic!("Could not write to GITHUB_OUTPUT file: {}", e);
             }
@@ -119,12 +139,17 @@ impl RestApiClient for GithubApiClient {
             "{} clang-tidy-checks-failed",
             tidy_checks_failed.unwrap_or(0)
         );
+        log::info!(
+            "{} clang-tidy errors, {} warnings, and {} notes",
+            tidy_counts.errors,
+            tidy_counts.warnings,
+            tidy_counts.notes
+        );
         log::info!("{checks_failed} checks-failed");
         checks_failed
     }
 
     fn make_headers(&self, use_diff: Option<bool>) -> HeaderMap<HeaderValue> {
-        let gh_token = env::var("GITHUB_TOKEN");
         let mut headers = HeaderMap::new();
         let return_fmt = "application/vnd.github.".to_owned()
             + if use_diff.is_some_and(|val| val) {
@@ -136,44 +161,53 @@ impl RestApiClient for GithubApiClient {
         let user_agent =
             "Mozilla/5.0 (Windows NT 10.0; Win64; x64; rv:120.0) Gecko/20100101 Firefox/120.0";
         headers.insert("User-Agent", user_agent.parse().unwrap());
-        if let Ok(token) = gh_token {
-            headers.insert("Authorization", token.parse().unwrap());
+        if let Some(token) = &self.token {
+            headers.insert("Authorization", format!("token {token}").parse().unwrap());
         }
         headers
     }
 
+    fn get_full_report_url(&self) -> Option<String> {
+        self.run_url.clone()
+    }
+
     fn get_list_of_changed_files(
         &self,
         extensions: &[&str],
-        ignored: &[String],
-        not_ignored: &[String],
+        ignored: &PathSet,
+        not_ignored: &PathSet,
     ) -> Vec<FileObj> {
-        if env::var("CI").is_ok_and(|val| val.as_str() == "true")
-            && self.repo.is_some()
-            && self.sha.is_some()
-        {
+        if let (true, Some(repo), Some(sha)) = (
+            env::var("CI").is_ok_and(|val| val.as_str() == "true"),
+            &self.repo,
+            &self.sha,
+        ) {
+            if self.event_name == "push" {
+                return self.get_push_changed_files(repo, sha, extensions, ignored, not_ignored);
+            }
+            if self.event_name == "merge_group" {
+                return self.get_compare_changed_files(
+                    repo,
+                    self.merge_group_range(),
+                    sha,
+                    extensions,
+                    ignored,
+                    not_ignored,
+                );
+            }
+            if let Some(pr_number) = self.pull_request_number() {
+                return self.get_pr_changed_files(
+                    repo,
+                    &pr_number.to_string(),
+                    extensions,
+                    ignored,
+                    not_ignored,
+                );
+            }
             // get diff from Github REST API
-            let url = format!(
-                "{}/repos/{}/{}",
-                self.api_url,
-                self.repo.as_ref().unwrap(),
-                if self.event_name == "pull_request" {
-                    let pr_number = &self.event_payload.as_ref().unwrap()["number"];
-                    format!("pulls/{}", &pr_number)
-                } else {
-                    format!("commits/{}", self.sha.as_ref().unwrap())
-                }
-            );
-            let response = self
-                .client
-                .get(url)
-                .headers(self.make_headers(Some(true)))
-                .send()
-                .unwrap()
-                .bytes()
-                .unwrap();
-
-            parse_diff_from_buf(&response, extensions, ignored, not_ignored)
+            let url = format!("{}/repos/{}/commits/{}", self.api_url, repo, sha);
+            let diff = self.fetch_diff(&url).unwrap_or_default();
+            parse_diff_from_buf(&diff, extensions, ignored, not_ignored)
         } else {
             // get diff from libgit2 API
             let repo = open_repo(".")
@@ -188,79 +222,365 @@ impl RestApiClient for GithubApiClient {
         files: &[FileObj],
         format_advice: &[FormatAdvice],
         tidy_advice: &[Vec<TidyNotification>],
-        thread_comments: &str,
-        no_lgtm: bool,
-        step_summary: bool,
-        file_annotations: bool,
-        style: &str,
-    ) {
-        let (comment, format_checks_failed, tidy_checks_failed) =
-            self.make_comment(files, format_advice, tidy_advice);
+        tool_errors: &[ToolError],
+        user_inputs: &FeedbackInput,
+    ) -> i32 {
+        // thread comments and check run summaries are limited in length
+        let (comment, full_report, format_checks_failed, tidy_checks_failed) = self.make_comment(
+            files,
+            format_advice,
+            tidy_advice,
+            tool_errors,
+            user_inputs,
+            Some(MAX_COMMENT_LEN),
+        );
+        let tidy_counts = user_inputs.failure_policy.count(files, tidy_advice);
+        let tool_checks_failed = if user_inputs.fail_on_tool_errors {
+            tool_errors.len() as i32
+        } else {
+            0
+        };
+        let truncated = comment != full_report;
+        let thread_comments = user_inputs.thread_comments.as_str();
         if thread_comments != "false" {
             // post thread comment for PR or push event
-            if let Some(repo) = &self.repo {
-                let is_pr = self.event_name == "pull_request";
-                let base_url = format!("{}/repos/{}/", &self.api_url, &repo);
-                let comments_url = if is_pr {
-                    format!(
-                        "{base_url}issues/{}",
-                        &self.event_payload.as_ref().unwrap()["number"]
-                    )
+            if self.event_name == "merge_group" {
+                // the merge group's temporary commit is not a useful place for a comment
+                log::info!("Thread comments are not posted for merge_group events");
+            } else if let Some(repo) = &self.repo {
+                let base_url = format!("{}/repos/{}", &self.api_url, &repo);
+                let comments_url = if let Some(pr_number) = self.pull_request_number() {
+                    Some(format!("{base_url}/issues/{pr_number}/comments"))
                 } else {
-                    format!("{base_url}/commits/{}", &self.sha.as_ref().unwrap())
+                    self.sha
+                        .as_ref()
+                        .map(|sha| format!("{base_url}/commits/{sha}/comments"))
                 };
-
-                // get count of comments
-                let request = self
-                    .client
-                    .get(&comments_url)
-                    .headers(self.make_headers(None))
-                    .send();
-                if let Ok(response) = request {
-                    let json = response.json::<serde_json::Value>().unwrap();
-                    let count = if is_pr {
-                        json["comments"].as_u64().unwrap()
-                    } else {
-                        json["commit"]["comment_count"].as_u64().unwrap()
-                    };
-                    let user_id: u64 = 41898282;
+                if let Some(comments_url) = comments_url {
                     self.update_comment(
-                        &format!("{}/comments", &comments_url),
+                        &comments_url,
                         &comment,
-                        count,
-                        user_id,
-                        no_lgtm,
-                        format_checks_failed + tidy_checks_failed == 0,
+                        &make_comment_marker(&user_inputs.comment_key),
+                        user_inputs.no_lgtm,
+                        format_checks_failed + tidy_checks_failed == 0 && tool_errors.is_empty(),
                         thread_comments == "update",
                     );
                 } else {
-                    let error = request.unwrap_err();
-                    if let Some(status) = error.status() {
-                        log::error!(
-                            "Could not get comment count. Got response {:?} from {comments_url}",
-                            status
-                        );
-                    } else {
-                        log::error!("attempt GET comment count failed");
-                    }
+                    log::error!("Could not determine where to post the thread comment");
                 }
             }
         }
-        if file_annotations {
-            self.post_annotations(files, format_advice, tidy_advice, style);
+        if user_inputs.file_annotations {
+            self.post_annotations(
+                files,
+                format_advice,
+                tidy_advice,
+                tool_errors,
+                &user_inputs.style,
+            );
+        }
+        if user_inputs.check_run {
+            let annotations = make_check_annotations(
+                files,
+                format_advice,
+                tidy_advice,
+                tool_errors,
+                &user_inputs.style,
+            );
+            self.post_check_run(
+                &comment,
+                &annotations,
+                format_checks_failed + tidy_checks_failed + tool_checks_failed,
+                user_inputs,
+            );
         }
-        if step_summary {
-            self.post_step_summary(&comment);
+        if user_inputs.step_summary || truncated {
+            // a truncated comment links to the full report in the step summary
+            self.post_step_summary(&full_report);
         }
         self.set_exit_code(
-            format_checks_failed + tidy_checks_failed,
+            format_checks_failed + tidy_checks_failed + tool_checks_failed,
             Some(format_checks_failed),
             Some(tidy_checks_failed),
-        );
+            Some(&tidy_counts),
+        )
     }
 }
 
 impl GithubApiClient {
+    /// Get the number of the pull request associated with the event that triggered the
+    /// workflow.
+    ///
+    /// - For `pull_request` and `pull_request_target` events, this is the `number` in
+    ///   the event payload.
+    /// - For `workflow_dispatch` events, this is the value of a `pr-number` (or
+    ///   `pr_number`) workflow input (if given).
+    ///
+    /// Returns [`None`] for any other event (or if no pull request number is available).
+    pub fn pull_request_number(&self) -> Option<u64> {
+        let payload = self.event_payload.as_ref()?;
+        match self.event_name.as_str() {
+            "pull_request" | "pull_request_target" => payload["number"].as_u64(),
+            "workflow_dispatch" => ["pr-number", "pr_number"].iter().find_map(|name| {
+                let input = &payload["inputs"][name];
+                // workflow inputs are strings unless the input's type is number
+                input
+                    .as_u64()
+                    .or_else(|| input.as_str().and_then(|val| val.trim().parse().ok()))
+            }),
+            _ => None,
+        }
+    }
+
+    /// Get the `base_sha` and `head_sha` (in respective order) of a `merge_group` event's
+    /// payload.
+    fn merge_group_range(&self) -> Option<(&str, &str)> {
+        let merge_group = &self.event_payload.as_ref()?["merge_group"];
+        Some((
+            merge_group["base_sha"].as_str()?,
+            merge_group["head_sha"].as_str()?,
+        ))
+    }
+
+    /// Send the given `request` using the client's [`RetryPolicy`] (see
+    /// [`send_api_request()`]).
+    fn send_request(&self, request: RequestBuilder) -> Result<Response, RestApiError> {
+        send_api_request(&self.client, request, &self.retry_policy)
+    }
+
+    /// Fetch a diff from the given REST API `url`.
+    ///
+    /// Returns [`None`] if the request failed or the response status was not successful.
+    fn fetch_diff(&self, url: &str) -> Option<Vec<u8>> {
+        match self.send_request(self.client.get(url).headers(self.make_headers(Some(true)))) {
+            Ok(response) if response.status().is_success() => {
+                response.bytes().ok().map(|buf| buf.to_vec())
+            }
+            Ok(response) => {
+                log::warn!("Got {} response from {url}", response.status());
+                None
+            }
+            Err(e) => {
+                log::warn!("Failed to get diff from {url}: {e}");
+                None
+            }
+        }
+    }
+
+    /// Get the list of files changed by all commits in a `push` event.
+    ///
+    /// The `before` and `after` SHAs in the event payload are compared (see
+    /// [`GithubApiClient::get_compare_changed_files()`]). If the push created a new
+    /// branch (`before` is all zeros), then the repository's default branch is compared
+    /// with `after` instead.
+    fn get_push_changed_files(
+        &self,
+        repo: &str,
+        sha: &str,
+        extensions: &[&str],
+        ignored: &PathSet,
+        not_ignored: &PathSet,
+    ) -> Vec<FileObj> {
+        let payload = self.event_payload.as_ref();
+        let before = payload.and_then(|p| p["before"].as_str());
+        let after = payload.and_then(|p| p["after"].as_str()).unwrap_or(sha);
+        let base = match before {
+            Some(before) if before.chars().all(|c| c == '0') => {
+                // a new branch was pushed; compare with the default branch (if not the same)
+                let default_branch =
+                    payload.and_then(|p| p["repository"]["default_branch"].as_str());
+                let pushed_ref = payload.and_then(|p| p["ref"].as_str()).unwrap_or_default();
+                default_branch
+                    .filter(|branch| pushed_ref != format!("refs/heads/{branch}"))
+                    .map(|branch| branch.to_string())
+            }
+            Some(before) => {
+                if payload.is_some_and(|p| p["forced"].as_bool().unwrap_or(false)) {
+                    log::info!(
+                        "Push event was forced; comparing {before} with {after} by merge-base"
+                    );
+                }
+                Some(before.to_string())
+            }
+            None => None,
+        };
+
+        self.get_compare_changed_files(
+            repo,
+            base.as_deref().map(|base| (base, after)),
+            sha,
+            extensions,
+            ignored,
+            not_ignored,
+        )
+    }
+
+    /// Get the list of files changed between the given `range` of commits (as a tuple
+    /// of `base` and `head` refs).
+    ///
+    /// The `range` is compared using the REST API's compare endpoint (from the
+    /// merge-base of `base` and `head`). If the REST API cannot compare the `range` (eg.
+    /// `base` was lost to a force-push), then the `range` is compared using the local
+    /// repository. If that also fails (or no `range` is given), then only the `sha`
+    /// commit's changes are used.
+    fn get_compare_changed_files(
+        &self,
+        repo: &str,
+        range: Option<(&str, &str)>,
+        sha: &str,
+        extensions: &[&str],
+        ignored: &PathSet,
+        not_ignored: &PathSet,
+    ) -> Vec<FileObj> {
+        if let Some((base, after)) = range {
+            let url = format!("{}/repos/{repo}/compare/{base}...{after}", self.api_url);
+            if let Some(diff) = self.fetch_diff(&url) {
+                return parse_diff_from_buf(&diff, extensions, ignored, not_ignored);
+            }
+            // try the local repository instead
+            if let Ok(local_repo) = open_repo(".") {
+                match get_diff_from_refs(&local_repo, base, Some(after)) {
+                    Ok(diff) => return parse_diff(&diff, extensions, ignored, not_ignored),
+                    Err(e) => log::warn!(
+                        "Could not compare {base}...{after} locally: {}",
+                        e.message()
+                    ),
+                }
+            }
+        }
+        log::warn!("Only using the changes from commit {sha}");
+        let url = format!("{}/repos/{repo}/commits/{sha}", self.api_url);
+        let diff = self.fetch_diff(&url).unwrap_or_default();
+        parse_diff_from_buf(&diff, extensions, ignored, not_ignored)
+    }
+
+    /// Get the list of files changed by a pull request.
+    ///
+    /// The pull request's diff is truncated (or refused) by GitHub for very large pull
+    /// requests. If the diff is not available or it describes fewer files than the pull
+    /// request's `changed_files` count, then the list of files is assembled from the
+    /// paginated `pulls/{pr_number}/files` endpoint instead. The count is taken from the
+    /// event payload if it describes the pull request (see
+    /// [`GithubApiClient::get_pr_changed_files_count()`]).
+    fn get_pr_changed_files(
+        &self,
+        repo: &str,
+        pr_number: &str,
+        extensions: &[&str],
+        ignored: &PathSet,
+        not_ignored: &PathSet,
+    ) -> Vec<FileObj> {
+        let url = format!("{}/repos/{repo}/pulls/{pr_number}", self.api_url);
+        if let Some(diff) = self.fetch_diff(&url) {
+            let diff_count = diff
+                .split(|byte| byte == &b'\n')
+                .filter(|line| line.starts_with(b"diff --git "))
+                .count() as u64;
+            match self.get_pr_changed_files_count(&url) {
+                Some(count) if diff_count < count => log::warn!(
+                    "The diff for PR #{pr_number} was truncated ({diff_count} of {count} files)"
+                ),
+                _ => return parse_diff_from_buf(&diff, extensions, ignored, not_ignored),
+            }
+        }
+        log::info!("Getting the list of changed files for PR #{pr_number} page by page");
+        self.get_pr_files_paginated(repo, pr_number, extensions, ignored, not_ignored)
+    }
+
+    /// Get the number of files changed by the pull request at `url`.
+    ///
+    /// The `pull_request` in the event payload is used if available. Other events (eg.
+    /// `workflow_dispatch`) do not describe the pull request, so it is requested from
+    /// the `pulls/{pr_number}` REST API endpoint instead. Returns [`None`] if the count
+    /// is not available.
+    fn get_pr_changed_files_count(&self, url: &str) -> Option<u64> {
+        let count = self
+            .event_payload
+            .as_ref()
+            .and_then(|p| p["pull_request"]["changed_files"].as_u64());
+        if count.is_some() {
+            return count;
+        }
+        match self
+            .send_request(self.client.get(url).headers(self.make_headers(None)))
+            .and_then(|response| {
+                response
+                    .error_for_status()
+                    .and_then(|response| response.json::<serde_json::Value>())
+                    .map_err(RestApiError::Request)
+            }) {
+            Ok(pull_request) => pull_request["changed_files"].as_u64(),
+            Err(e) => {
+                log::warn!("Failed to get the number of changed files from {url}: {e}");
+                None
+            }
+        }
+    }
+
+    /// Assemble the list of files changed by a pull request from the paginated
+    /// `pulls/{pr_number}/files` endpoint.
+    ///
+    /// All pages are traversed (using the `Link` header of each response). Each file's
+    /// `patch` is parsed like a diff. GitHub omits the `patch` for some files (eg. if
+    /// the patch is too large), so a warning is shown for such source files.
+    fn get_pr_files_paginated(
+        &self,
+        repo: &str,
+        pr_number: &str,
+        extensions: &[&str],
+        ignored: &PathSet,
+        not_ignored: &PathSet,
+    ) -> Vec<FileObj> {
+        let mut diff = String::new();
+        let mut next_page = Some(format!(
+            "{}/repos/{repo}/pulls/{pr_number}/files?per_page={PER_PAGE}",
+            self.api_url
+        ));
+        while let Some(url) = next_page {
+            let pr_files = match self
+                .send_request(self.client.get(&url).headers(self.make_headers(None)))
+                .and_then(|response| {
+                    let page = get_next_page_url(response.headers());
+                    response
+                        .error_for_status()
+                        .and_then(|response| response.json::<Vec<PullRequestFile>>())
+                        .map(|pr_files| (pr_files, page))
+                        .map_err(RestApiError::Request)
+                }) {
+                Ok((pr_files, page)) => {
+                    next_page = page;
+                    pr_files
+                }
+                Err(e) => {
+                    log::error!("Failed to get the changed files from {url}: {e}");
+                    break;
+                }
+            };
+            for pr_file in &pr_files {
+                if pr_file.status == "removed" {
+                    continue;
+                }
+                if let Some(patch) = &pr_file.patch {
+                    diff.push_str(&format!(
+                        "diff --git a/{name} b/{name}\n--- a/{name}\n+++ b/{name}\n{patch}\n",
+                        name = pr_file.filename,
+                    ));
+                } else if is_source_or_ignored(
+                    Path::new(&pr_file.filename),
+                    extensions,
+                    ignored,
+                    not_ignored,
+                ) {
+                    log::warn!(
+                        "GitHub omitted the patch for {}; it will not be analyzed",
+                        pr_file.filename
+                    );
+                }
+            }
+        }
+        parse_diff_from_buf(diff.as_bytes(), extensions, ignored, not_ignored)
+    }
+
     fn post_step_summary(&self, comment: &String) {
         if let Ok(gh_out) = env::var("GITHUB_STEP_SUMMARY") {
             let mut gh_out_file = OpenOptions::new()
@@ -278,196 +598,1340 @@ impl GithubApiClient {
         files: &[FileObj],
         format_advice: &[FormatAdvice],
         tidy_advice: &[Vec<TidyNotification>],
+        tool_errors: &[ToolError],
         style: &str,
     ) {
-        if !format_advice.is_empty() {
-            // formalize the style guide name
-            let style_guide =
-                if ["google", "chromium", "microsoft", "mozilla", "webkit"].contains(&style) {
-                    // capitalize the first letter
-                    let mut char_iter = style.chars();
-                    match char_iter.next() {
-                        None => String::new(),
-                        Some(f) => f.to_uppercase().collect::<String>() + char_iter.as_str(),
-                    }
-                } else if style == "llvm" || style == "gnu" {
-                    style.to_ascii_uppercase()
-                } else {
-                    String::from("Custom")
-                };
+        for command in
+            make_workflow_annotations(files, format_advice, tidy_advice, tool_errors, style)
+        {
+            println!("{command}");
+        }
+    }
 
-            // iterate over clang-format advice and post annotations
-            for (index, advice) in format_advice.iter().enumerate() {
-                // assemble a list of line numbers
-                let mut lines: Vec<usize> = Vec::new();
-                for replacement in &advice.replacements {
-                    if let Some(line_int) = replacement.line {
-                        if !lines.contains(&line_int) {
-                            lines.push(line_int);
-                        }
-                    }
-                }
-                // post annotation if any applicable lines were formatted
-                if !lines.is_empty() {
-                    println!(
-                        "::notice file={name},title=Run clang-format on {name}::File {name} does not conform to {style_guide} style guidelines. (lines {line_set})",
-                        name = &files[index].name.to_string_lossy().replace('\\', "/"),
-                        line_set = lines.iter().map(|val| val.to_string()).collect::<Vec<_>>().join(","),
-                    );
-                }
+    /// Create a completed check run (using the Checks API) with the given `summary` and
+    /// `annotations`.
+    ///
+    /// The Checks API accepts at most 50 annotations per request, so the check run is
+    /// created with the first batch of `annotations` and then updated with each
+    /// remaining batch. The check run's conclusion is determined by the number of
+    /// `checks_failed` (see [`FeedbackInput::check_failure_threshold`]).
+    fn post_check_run(
+        &self,
+        summary: &str,
+        annotations: &[CheckAnnotation],
+        checks_failed: i32,
+        user_inputs: &FeedbackInput,
+    ) {
+        let Some(repo) = &self.repo else {
+            log::error!("Could not determine the repository for the check run");
+            return;
+        };
+        // a pull request's check runs belong to the head commit (not the merge commit)
+        let head_sha = self
+            .event_payload
+            .as_ref()
+            .and_then(|payload| payload["pull_request"]["head"]["sha"].as_str())
+            .or(self.sha.as_deref());
+        let Some(head_sha) = head_sha else {
+            log::error!("Could not determine the commit for the check run");
+            return;
+        };
+        let name = if user_inputs.comment_key.is_empty() {
+            String::from("cpp-linter")
+        } else {
+            format!("cpp-linter ({})", user_inputs.comment_key)
+        };
+        let conclusion = get_check_conclusion(checks_failed, user_inputs.check_failure_threshold);
+        let title = format!("{checks_failed} check(s) failed");
+        let mut batches = annotations.chunks(MAX_ANNOTATIONS);
+        let payload = CheckRunPayload {
+            name: Some(&name),
+            head_sha: Some(head_sha),
+            status: Some("completed"),
+            conclusion: Some(conclusion),
+            output: CheckRunOutput {
+                title: &title,
+                summary,
+                annotations: batches.next().unwrap_or_default(),
+            },
+        };
+        let url = format!("{}/repos/{repo}/check-runs", self.api_url);
+        let check_run = match self.send_request(
+            self.client
+                .post(&url)
+                .headers(self.make_headers(None))
+                .json(&payload),
+        ) {
+            Ok(response) if response.status().is_success() => response.json::<CheckRun>(),
+            Ok(response) => {
+                log::error!("Got {} response from creating check run", response.status());
+                return;
+            }
+            Err(e) => {
+                log::error!("Failed to create check run: {e}");
+                return;
             }
-        } // end format_advice iterations
-
-        // iterate over clang-tidy advice and post annotations
-        // The tidy_advice vector is parallel to the files vector; meaning it serves as a file filterer.
-        // lines are already filter as specified to clang-tidy CLI.
-        for (index, advice) in tidy_advice.iter().enumerate() {
-            for note in advice {
-                if note.filename == files[index].name.to_string_lossy().replace('\\', "/") {
-                    println!(
-                        "::{severity} file={file},line={line},title={file}:{line}:{cols} [{diag}]::{info}",
-                        severity = if note.severity == *"note" { "notice".to_string() } else {note.severity.clone()},
-                        file = note.filename,
-                        line = note.line,
-                        cols = note.cols,
-                        diag = note.diagnostic,
-                        info = note.rationale,
+        };
+        let Ok(check_run) = check_run else {
+            log::error!("Failed to parse the created check run");
+            return;
+        };
+        log::info!("Created check run {} ({conclusion})", check_run.id);
+        for batch in batches {
+            let payload = CheckRunPayload {
+                name: None,
+                head_sha: None,
+                status: None,
+                conclusion: None,
+                output: CheckRunOutput {
+                    title: &title,
+                    summary,
+                    annotations: batch,
+                },
+            };
+            match self.send_request(
+                self.client
+                    .patch(format!("{url}/{}", check_run.id))
+                    .headers(self.make_headers(None))
+                    .json(&payload),
+            ) {
+                Ok(response) if response.status().is_success() => {}
+                Ok(response) => {
+                    log::error!(
+                        "Got {} response from adding annotations to check run",
+                        response.status()
                     );
+                    return;
+                }
+                Err(e) => {
+                    log::error!("Failed to add annotations to check run: {e}");
+                    return;
                 }
             }
         }
     }
 
-    /// update existing comment or remove old comment(s) and post a new comment
-    #[allow(clippy::too_many_arguments)]
+    /// Post the given `comment` to the thread at `comments_url`, and remove (or update)
+    /// any outdated comments previously posted by the bot with the same `marker`.
+    ///
+    /// - If `update_only` is true, then the oldest bot comment is updated and any other
+    ///   bot comments are deleted.
+    /// - Otherwise, all bot comments are deleted and a new comment is posted.
+    ///
+    /// If `is_lgtm` and `no_lgtm` are both true, then all bot comments are deleted and no
+    /// new comment is posted.
     fn update_comment(
         &self,
-        url: &String,
-        comment: &String,
-        count: u64,
-        user_id: u64,
+        comments_url: &str,
+        comment: &str,
+        marker: &str,
         no_lgtm: bool,
         is_lgtm: bool,
         update_only: bool,
     ) {
-        let comment_url =
-            self.remove_bot_comments(url, user_id, count, !update_only || (is_lgtm && no_lgtm));
-        #[allow(clippy::nonminimal_bool)] // an inaccurate assessment
-        if (is_lgtm && !no_lgtm) || !is_lgtm {
-            let payload = HashMap::from([("body", comment)]);
-            log::debug!("payload body:\n{:?}", payload);
-            let req_meth = if comment_url.is_some() {
-                Method::PATCH
-            } else {
-                Method::POST
-            };
-            if let Ok(response) = self
-                .client
-                .request(
-                    req_meth.clone(),
-                    if let Some(_url) = comment_url {
-                        _url
-                    } else {
-                        url.to_string()
-                    },
-                )
+        let user_id = self.get_bot_user_id();
+        let bot_comments = match self.list_bot_comments(comments_url, marker, user_id) {
+            Ok(bot_comments) => bot_comments,
+            Err(e) => {
+                log::error!("Failed to get list of existing comments: {e}");
+                return;
+            }
+        };
+        let post_new = !(is_lgtm && no_lgtm);
+        let mut outdated = bot_comments.into_iter();
+        let existing = if update_only && post_new {
+            outdated.next()
+        } else {
+            None
+        };
+        for bot_comment in outdated {
+            self.delete_comment(&bot_comment);
+        }
+        if !post_new {
+            return;
+        }
+
+        let payload = HashMap::from([("body", comment)]);
+        log::debug!("payload body:\n{:?}", payload);
+        let (req_meth, url) = match &existing {
+            Some(bot_comment) => (Method::PATCH, bot_comment.url.as_str()),
+            None => (Method::POST, comments_url),
+        };
+        match self.send_request(
+            self.client
+                .request(req_meth.clone(), url)
                 .headers(self.make_headers(None))
-                .json(&payload)
-                .send()
-            {
+                .json(&payload),
+        ) {
+            Ok(response) if response.status().is_success() => {
                 log::info!(
                     "Got {} response from {:?}ing comment",
                     response.status(),
                     req_meth,
                 );
             }
+            Ok(response) => log::error!(
+                "Got {} response from {:?}ing comment",
+                response.status(),
+                req_meth,
+            ),
+            Err(e) => log::error!("Failed to {:?} comment: {e}", req_meth),
+        }
+    }
+
+    /// Get the user id of the account that owns the `GITHUB_TOKEN`.
+    ///
+    /// The identity is requested from the `user` REST API endpoint. Returns [`None`] for
+    /// tokens that cannot access this endpoint, like the `GITHUB_TOKEN` provided to
+    /// workflows or any other GitHub App's installation token.
+    fn get_bot_user_id(&self) -> Option<u64> {
+        self.token.as_ref()?;
+        let url = format!("{}/user", self.api_url);
+        match self.send_request(self.client.get(url).headers(self.make_headers(None))) {
+            Ok(response) if response.status().is_success() => match response.json::<User>() {
+                Ok(user) => {
+                    log::info!("Posting comments as {} ({})", user.login, user.id);
+                    return Some(user.id);
+                }
+                Err(e) => log::debug!("Failed to parse the token's user: {e}"),
+            },
+            Ok(response) => log::debug!("Got {} response from GET /user", response.status()),
+            Err(e) => log::debug!("Failed to get the token's user: {e}"),
         }
+        log::info!("Assuming comments are posted as {GITHUB_ACTIONS_LOGIN}");
+        None
     }
 
-    fn remove_bot_comments(
+    /// Get the list of comments previously posted by the bot (with the given `marker`)
+    /// in the thread at `comments_url`.
+    ///
+    /// If the bot's `user_id` is unknown (see [`GithubApiClient::get_bot_user_id()`]),
+    /// then the comments are assumed to be posted by [`GITHUB_ACTIONS_LOGIN`] (the
+    /// account of the workflow's `GITHUB_TOKEN`). Comments from other GitHub Apps are
+    /// never considered in that case.
+    ///
+    /// All pages of comments are traversed (using the `Link` header of each response).
+    /// The returned comments are in chronological order.
+    fn list_bot_comments(
         &self,
-        url: &String,
-        count: u64,
-        user_id: u64,
-        delete: bool,
-    ) -> Option<String> {
-        let mut page = 1;
-        let mut comment_url = None;
-        let mut total = count;
-        while total > 0 {
-            let request = self.client.get(format!("{url}/?page={page}")).send();
-            if request.is_err() {
-                log::error!("Failed to get list of existing comments");
-                return None;
-            } else if let Ok(response) = request {
-                let payload: JsonCommentsPayload = response.json().unwrap();
-                let mut comment_count = 0;
-                for comment in payload.comments {
-                    if comment.body.starts_with("<!-- cpp linter action -->")
-                        && comment.user.id == user_id
-                    {
-                        log::debug!(
-                            "comment id {} from user {} ({})",
-                            comment.id,
-                            comment.user.login,
-                            comment.user.id,
-                        );
-                        #[allow(clippy::nonminimal_bool)] // an inaccurate assessment
-                        if delete || (!delete && comment_url.is_none()) {
-                            // if not updating: remove all outdated comments
-                            // if updating: remove all outdated comments except the last one
-
-                            // use last saved comment_url (if not None) or current comment url
-                            let del_url = if let Some(last_url) = &comment_url {
-                                last_url
-                            } else {
-                                &comment.url
-                            };
-                            if let Ok(response) = self
-                                .client
-                                .delete(del_url)
-                                .headers(self.make_headers(None))
-                                .send()
-                            {
-                                log::info!(
-                                    "Got {} from DELETE {}",
-                                    response.status(),
-                                    del_url.strip_prefix(&self.api_url).unwrap(),
-                                )
-                            } else {
-                                log::error!("Unable to remove old bot comment");
-                                return None; // exit early as this is most likely due to rate limit.
-                            }
-                        }
-                        if !delete {
-                            comment_url = Some(comment.url)
-                        }
+        comments_url: &str,
+        marker: &str,
+        user_id: Option<u64>,
+    ) -> Result<Vec<ThreadComment>, RestApiError> {
+        let mut bot_comments = Vec::new();
+        let mut next_page = Some(format!("{comments_url}?per_page={PER_PAGE}"));
+        while let Some(url) = next_page {
+            let response =
+                self.send_request(self.client.get(&url).headers(self.make_headers(None)))?;
+            if !response.status().is_success() {
+                return Err(RestApiError::Status(response.status()));
+            }
+            next_page = get_next_page_url(response.headers());
+            let comments = response
+                .json::<Vec<ThreadComment>>()
+                .map_err(RestApiError::Request)?;
+            for comment in comments {
+                let is_bot = match user_id {
+                    Some(id) => comment.user.id == id,
+                    None => comment.user.login == GITHUB_ACTIONS_LOGIN,
+                };
+                if comment.body.starts_with(marker) && is_bot {
+                    log::debug!(
+                        "comment id {} from user {} ({})",
+                        comment.id,
+                        comment.user.login,
+                        comment.user.id,
+                    );
+                    bot_comments.push(comment);
+                }
+            }
+        }
+        Ok(bot_comments)
+    }
+
+    /// Delete the given `comment`.
+    fn delete_comment(&self, comment: &ThreadComment) {
+        match self.send_request(
+            self.client
+                .delete(&comment.url)
+                .headers(self.make_headers(None)),
+        ) {
+            Ok(response) => log::info!(
+                "Got {} from DELETE {}",
+                response.status(),
+                comment
+                    .url
+                    .strip_prefix(&self.api_url)
+                    .unwrap_or(&comment.url),
+            ),
+            Err(e) => log::error!("Unable to remove old bot comment: {e}"),
+        }
+    }
+}
+
+/// Make the workflow commands that annotate the concerns in `format_advice` and
+/// `tidy_advice` about the given set of `files`, and the `tool_errors` (if any).
+fn make_workflow_annotations(
+    files: &[FileObj],
+    format_advice: &[FormatAdvice],
+    tidy_advice: &[Vec<TidyNotification>],
+    tool_errors: &[ToolError],
+    style: &str,
+) -> Vec<String> {
+    let mut commands = Vec::new();
+    for error in tool_errors {
+        commands.push(format!(
+            "::error file={file},title={title}::{message}",
+            file = escape_workflow_property(&error.filename),
+            title =
+                escape_workflow_property(&format!("{} failed on {}", error.tool, error.filename)),
+            message = escape_workflow_data(&error.message),
+        ));
+    }
+
+    if !format_advice.is_empty() {
+        let style_guide = formalize_style_name(style);
+
+        // iterate over clang-format advice and post annotations
+        for (index, advice) in format_advice.iter().enumerate() {
+            // assemble a list of line numbers
+            let mut lines: Vec<usize> = Vec::new();
+            for replacement in &advice.replacements {
+                if let Some(line_int) = replacement.line {
+                    if !lines.contains(&line_int) {
+                        lines.push(line_int);
                     }
-                    comment_count += 1;
                 }
-                total -= comment_count;
-                page += 1;
+            }
+            // post annotation if any applicable lines were formatted
+            if !lines.is_empty() {
+                let name = files[index].name.to_string_lossy().replace('\\', "/");
+                commands.push(format!(
+                    "::notice file={file},title={title}::{message}",
+                    file = escape_workflow_property(&name),
+                    title = escape_workflow_property(&format!("Run clang-format on {name}")),
+                    message = escape_workflow_data(&format!(
+                        "File {name} does not conform to {style_guide} style guidelines. (lines {})",
+                        lines
+                            .iter()
+                            .map(|val| val.to_string())
+                            .collect::<Vec<_>>()
+                            .join(","),
+                    )),
+                ));
             }
         }
-        comment_url
+    } // end format_advice iterations
+
+    // iterate over clang-tidy advice and post annotations
+    // The tidy_advice vector is parallel to the files vector; meaning it serves as a file filterer.
+    // lines are already filter as specified to clang-tidy CLI.
+    for (index, advice) in tidy_advice.iter().enumerate() {
+        for note in advice {
+            if note.filename == files[index].name.to_string_lossy().replace('\\', "/") {
+                commands.push(format!(
+                    "::{severity} file={file},line={line},title={title}::{info}",
+                    severity = if note.severity == *"note" {
+                        "notice".to_string()
+                    } else {
+                        note.severity.clone()
+                    },
+                    file = escape_workflow_property(&note.filename),
+                    line = note.line,
+                    title = escape_workflow_property(&format!(
+                        "{}:{}:{} [{}]",
+                        note.filename, note.line, note.cols, note.diagnostic
+                    )),
+                    info = escape_workflow_data(&note.rationale),
+                ));
+            }
+        }
+    }
+    commands
+}
+
+/// Escape the given `data` (eg. an annotation's message) for a workflow command.
+///
+/// Workflow commands end at a line break, so line breaks are percent-encoded (after
+/// any `%` that would otherwise start an escape sequence).
+fn escape_workflow_data(data: &str) -> String {
+    data.replace('%', "%25")
+        .replace('\r', "%0D")
+        .replace('\n', "%0A")
+}
+
+/// Escape the given `value` of a workflow command's property (eg. an annotation's
+/// `file` or `title`).
+///
+/// In addition to the escaping done by [`escape_workflow_data()`], the `:` and `,`
+/// that separate properties are percent-encoded.
+fn escape_workflow_property(value: &str) -> String {
+    escape_workflow_data(value)
+        .replace(':', "%3A")
+        .replace(',', "%2C")
+}
+
+/// Get a formal name for the given clang-format `style` (as used in annotations).
+fn formalize_style_name(style: &str) -> String {
+    if ["google", "chromium", "microsoft", "mozilla", "webkit"].contains(&style) {
+        // capitalize the first letter
+        let mut char_iter = style.chars();
+        match char_iter.next() {
+            None => String::new(),
+            Some(f) => f.to_uppercase().collect::<String>() + char_iter.as_str(),
+        }
+    } else if style == "llvm" || style == "gnu" {
+        style.to_ascii_uppercase()
+    } else {
+        String::from("Custom")
+    }
+}
+
+/// Get the conclusion of a check run based on the number of `checks_failed` and the
+/// `--check-failure-threshold`.
+fn get_check_conclusion(checks_failed: i32, threshold: u32) -> &'static str {
+    if checks_failed <= 0 {
+        "success"
+    } else if threshold > 0 && checks_failed as u32 >= threshold {
+        "failure"
+    } else {
+        "neutral"
+    }
+}
+
+/// Create the check run annotations about the concerns in `format_advice` and
+/// `tidy_advice`, and the `tool_errors`.
+///
+/// These are similar to the annotations created by
+/// [`GithubApiClient::post_annotations()`], but clang-tidy annotations also include
+/// the suggested fix (if any).
+fn make_check_annotations(
+    files: &[FileObj],
+    format_advice: &[FormatAdvice],
+    tidy_advice: &[Vec<TidyNotification>],
+    tool_errors: &[ToolError],
+    style: &str,
+) -> Vec<CheckAnnotation> {
+    let mut annotations = Vec::new();
+    for error in tool_errors {
+        let (reason, details) = error
+            .message
+            .split_once('\n')
+            .unwrap_or((error.message.as_str(), ""));
+        annotations.push(CheckAnnotation {
+            path: error.filename.clone(),
+            start_line: 1,
+            end_line: 1,
+            annotation_level: "failure",
+            title: format!("{} failed on {}", error.tool, error.filename),
+            message: reason.to_string(),
+            raw_details: if details.is_empty() {
+                None
+            } else {
+                Some(details.to_string())
+            },
+        });
+    }
+    let style_guide = formalize_style_name(style);
+    for (index, advice) in format_advice.iter().enumerate() {
+        let mut lines: Vec<usize> = advice.replacements.iter().filter_map(|r| r.line).collect();
+        lines.dedup();
+        if let Some(first) = lines.first() {
+            let name = files[index].name.to_string_lossy().replace('\\', "/");
+            annotations.push(CheckAnnotation {
+                message: format!(
+                    "File {name} does not conform to {style_guide} style guidelines. (lines {})",
+                    lines
+                        .iter()
+                        .map(|val| val.to_string())
+                        .collect::<Vec<_>>()
+                        .join(",")
+                ),
+                title: format!("Run clang-format on {name}"),
+                path: name,
+                start_line: *first as u32,
+                end_line: *first as u32,
+                annotation_level: "notice",
+                raw_details: None,
+            });
+        }
+    }
+    for (index, advice) in tidy_advice.iter().enumerate() {
+        for note in advice {
+            if note.filename != files[index].name.to_string_lossy().replace('\\', "/") {
+                continue;
+            }
+            annotations.push(CheckAnnotation {
+                path: note.filename.clone(),
+                start_line: note.line,
+                end_line: note.line,
+                annotation_level: match note.severity.as_str() {
+                    "error" => "failure",
+                    "warning" => "warning",
+                    _ => "notice",
+                },
+                title: format!(
+                    "{}:{}:{} [{}]",
+                    note.filename, note.line, note.cols, note.diagnostic
+                ),
+                message: note.rationale.trim().to_string(),
+                raw_details: if note.suggestion.is_empty() {
+                    None
+                } else {
+                    Some(note.suggestion.join("\n"))
+                },
+            });
+        }
     }
+    annotations
 }
 
+/// The maximum number of annotations accepted per Checks API request.
+const MAX_ANNOTATIONS: usize = 50;
+
+/// The maximum number of items requested per page from paginated REST API endpoints.
+const PER_PAGE: usize = 100;
+
+/// The login of the bot account that owns the `GITHUB_TOKEN` provided to workflows.
+///
+/// Comments are assumed to be posted by this account when the token's user cannot be
+/// identified (see [`GithubApiClient::get_bot_user_id()`]).
+const GITHUB_ACTIONS_LOGIN: &str = "github-actions[bot]";
+
+/// A file changed by a pull request as described by the `pulls/{pr_number}/files`
+/// REST API endpoint.
 #[derive(Debug, Deserialize, PartialEq)]
-struct JsonCommentsPayload {
-    comments: Vec<Comment>,
+struct PullRequestFile {
+    pub filename: String,
+    pub status: String,
+    pub patch: Option<String>,
+}
+
+/// An annotation of a check run as described by the Checks API.
+#[derive(Debug, Serialize, PartialEq)]
+struct CheckAnnotation {
+    pub path: String,
+    pub start_line: u32,
+    pub end_line: u32,
+    pub annotation_level: &'static str,
+    pub title: String,
+    pub message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub raw_details: Option<String>,
+}
+
+/// The `output` of a check run as described by the Checks API.
+#[derive(Debug, Serialize)]
+struct CheckRunOutput<'a> {
+    pub title: &'a str,
+    pub summary: &'a str,
+    pub annotations: &'a [CheckAnnotation],
+}
+
+/// The payload used to create (or update) a check run.
+///
+/// Fields that are [`None`] are omitted (useful when updating a check run).
+#[derive(Debug, Serialize)]
+struct CheckRunPayload<'a> {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub head_sha: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub conclusion: Option<&'a str>,
+    pub output: CheckRunOutput<'a>,
 }
 
+/// A check run as described by the Checks API (only the fields needed are included).
+#[derive(Debug, Deserialize)]
+struct CheckRun {
+    pub id: u64,
+}
+
+/// A comment in a pull request's (or commit's) thread as described by the REST API.
 #[derive(Debug, Deserialize, PartialEq, Clone)]
-struct Comment {
-    pub id: i64,
+struct ThreadComment {
+    pub id: u64,
     pub url: String,
     pub body: String,
     pub user: User,
 }
 
+/// The author of a [`ThreadComment`].
 #[derive(Debug, Deserialize, PartialEq, Clone)]
 struct User {
     pub login: String,
     pub id: u64,
 }
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use reqwest::blocking::Client;
+    use serde_json::json;
+
+    use std::path::PathBuf;
+
+    use super::{
+        escape_workflow_data, escape_workflow_property, get_check_conclusion,
+        make_check_annotations, make_workflow_annotations, GithubApiClient, GITHUB_ACTIONS_LOGIN,
+    };
+    use crate::clang_tools::clang_format::{FormatAdvice, Replacement};
+    use crate::clang_tools::clang_tidy::TidyNotification;
+    use crate::clang_tools::ToolError;
+    use crate::common_fs::{FileObj, PathSet};
+    use crate::rest_api::{make_comment_marker, FeedbackInput, RetryPolicy};
+
+    const REPO: &str = "cpp-linter/test-repo";
+    const BEFORE: &str = "1111111111111111111111111111111111111111";
+    const AFTER: &str = "2222222222222222222222222222222222222222";
+    const ZERO_SHA: &str = "0000000000000000000000000000000000000000";
+
+    const DIFF: &str = "diff --git a/src/demo.cpp b/src/demo.cpp\n\
+                        --- a/src/demo.cpp\n\
+                        +++ b/src/demo.cpp\n\
+                        @@ -1,2 +1,3 @@\n \n+int a;\n \n";
+
+    fn test_client(api_url: &str, event_name: &str, payload: serde_json::Value) -> GithubApiClient {
+        GithubApiClient {
+            client: Client::new(),
+            retry_policy: RetryPolicy {
+                max_retries: 2,
+                base_delay: Duration::ZERO,
+                max_delay: Duration::from_secs(1),
+            },
+            event_payload: Some(payload),
+            event_name: event_name.to_string(),
+            api_url: api_url.to_string(),
+            repo: Some(REPO.to_string()),
+            sha: Some(AFTER.to_string()),
+            debug_enabled: false,
+            token: Some(String::from("test-token")),
+            run_url: None,
+        }
+    }
+
+    #[test]
+    fn push_compare_range() {
+        let mut server = mockito::Server::new();
+        let mock = server
+            .mock(
+                "GET",
+                format!("/repos/{REPO}/compare/{BEFORE}...{AFTER}").as_str(),
+            )
+            .with_body(DIFF)
+            .create();
+        let client = test_client(
+            &server.url(),
+            "push",
+            json!({"before": BEFORE, "after": AFTER, "forced": true}),
+        );
+        let files = client.get_push_changed_files(
+            REPO,
+            AFTER,
+            &["cpp"],
+            &PathSet::default(),
+            &PathSet::default(),
+        );
+        mock.assert();
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[0].added_lines, vec![2]);
+    }
+
+    #[test]
+    fn push_new_branch() {
+        let mut server = mockito::Server::new();
+        let mock = server
+            .mock(
+                "GET",
+                format!("/repos/{REPO}/compare/main...{AFTER}").as_str(),
+            )
+            .with_body(DIFF)
+            .create();
+        let client = test_client(
+            &server.url(),
+            "push",
+            json!({
+                "before": ZERO_SHA,
+                "after": AFTER,
+                "ref": "refs/heads/feature",
+                "repository": {"default_branch": "main"}
+            }),
+        );
+        let files = client.get_push_changed_files(
+            REPO,
+            AFTER,
+            &["cpp"],
+            &PathSet::default(),
+            &PathSet::default(),
+        );
+        mock.assert();
+        assert_eq!(files.len(), 1);
+    }
+
+    #[test]
+    fn push_fallback_to_commit() {
+        let mut server = mockito::Server::new();
+        let compare_mock = server
+            .mock(
+                "GET",
+                format!("/repos/{REPO}/compare/{BEFORE}...{AFTER}").as_str(),
+            )
+            .with_status(404)
+            .create();
+        let commit_mock = server
+            .mock("GET", format!("/repos/{REPO}/commits/{AFTER}").as_str())
+            .with_body(DIFF)
+            .create();
+        let client = test_client(
+            &server.url(),
+            "push",
+            json!({"before": BEFORE, "after": AFTER}),
+        );
+        let files = client.get_push_changed_files(
+            REPO,
+            AFTER,
+            &["cpp"],
+            &PathSet::default(),
+            &PathSet::default(),
+        );
+        compare_mock.assert();
+        commit_mock.assert();
+        assert_eq!(files.len(), 1);
+    }
+
+    #[test]
+    fn push_initial_default_branch() {
+        let mut server = mockito::Server::new();
+        let mock = server
+            .mock("GET", format!("/repos/{REPO}/commits/{AFTER}").as_str())
+            .with_body(DIFF)
+            .create();
+        let client = test_client(
+            &server.url(),
+            "push",
+            json!({
+                "before": ZERO_SHA,
+                "after": AFTER,
+                "ref": "refs/heads/main",
+                "repository": {"default_branch": "main"}
+            }),
+        );
+        let files = client.get_push_changed_files(
+            REPO,
+            AFTER,
+            &["cpp"],
+            &PathSet::default(),
+            &PathSet::default(),
+        );
+        mock.assert();
+        assert_eq!(files.len(), 1);
+    }
+
+    fn pr_payload(changed_files: u64) -> serde_json::Value {
+        json!({"number": 42, "pull_request": {"changed_files": changed_files}})
+    }
+
+    #[test]
+    fn pr_diff_complete() {
+        let mut server = mockito::Server::new();
+        let mock = server
+            .mock("GET", format!("/repos/{REPO}/pulls/42").as_str())
+            .with_body(DIFF)
+            .create();
+        let client = test_client(&server.url(), "pull_request", pr_payload(1));
+        let files = client.get_pr_changed_files(
+            REPO,
+            "42",
+            &["cpp"],
+            &PathSet::default(),
+            &PathSet::default(),
+        );
+        mock.assert();
+        assert_eq!(files.len(), 1);
+    }
+
+    fn mock_pr_files(server: &mut mockito::Server) -> (mockito::Mock, mockito::Mock) {
+        let page_1 = (0..super::PER_PAGE)
+            .map(|i| {
+                json!({
+                    "filename": format!("docs/page_{i}.md"),
+                    "status": "added",
+                    "patch": "@@ -0,0 +1 @@\n+docs"
+                })
+            })
+            .collect::<Vec<_>>();
+        let page_2 = json!([
+            {
+                "filename": "src/demo.cpp",
+                "status": "modified",
+                "patch": "@@ -1,2 +1,3 @@\n \n+int a;\n "
+            },
+            {"filename": "src/huge.cpp", "status": "modified"},
+            {"filename": "src/removed.cpp", "status": "removed"}
+        ]);
+        let path = format!("/repos/{REPO}/pulls/42/files?per_page=100");
+        let url = server.url();
+        (
+            server
+                .mock("GET", path.as_str())
+                .with_header("link", &format!("<{url}{path}&page=2>; rel=\"next\""))
+                .with_body(serde_json::to_string(&page_1).unwrap())
+                .create(),
+            server
+                .mock("GET", format!("{path}&page=2").as_str())
+                .with_body(page_2.to_string())
+                .create(),
+        )
+    }
+
+    #[test]
+    fn pr_diff_truncated() {
+        let mut server = mockito::Server::new();
+        let diff_mock = server
+            .mock("GET", format!("/repos/{REPO}/pulls/42").as_str())
+            .with_body(DIFF)
+            .create();
+        let (page_1, page_2) = mock_pr_files(&mut server);
+        let client = test_client(&server.url(), "pull_request", pr_payload(103));
+        let files = client.get_pr_changed_files(
+            REPO,
+            "42",
+            &["cpp"],
+            &PathSet::default(),
+            &PathSet::default(),
+        );
+        diff_mock.assert();
+        page_1.assert();
+        page_2.assert();
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[0].name.to_string_lossy(), "src/demo.cpp");
+        assert_eq!(files[0].added_lines, vec![2]);
+    }
+
+    #[test]
+    fn pr_diff_refused() {
+        let mut server = mockito::Server::new();
+        let diff_mock = server
+            .mock("GET", format!("/repos/{REPO}/pulls/42").as_str())
+            .with_status(406)
+            .create();
+        let (page_1, page_2) = mock_pr_files(&mut server);
+        let client = test_client(&server.url(), "pull_request", json!({"number": 42}));
+        let files = client.get_pr_changed_files(
+            REPO,
+            "42",
+            &["cpp", "md"],
+            &PathSet::default(),
+            &PathSet::default(),
+        );
+        diff_mock.assert();
+        page_1.assert();
+        page_2.assert();
+        assert_eq!(files.len(), super::PER_PAGE + 1);
+    }
+
+    #[test]
+    fn pr_number_from_events() {
+        for event in ["pull_request", "pull_request_target"] {
+            let client = test_client("", event, json!({"number": 42}));
+            assert_eq!(client.pull_request_number(), Some(42));
+        }
+        let client = test_client(
+            "",
+            "workflow_dispatch",
+            json!({"inputs": {"pr-number": "42"}}),
+        );
+        assert_eq!(client.pull_request_number(), Some(42));
+        let client = test_client("", "workflow_dispatch", json!({"inputs": {"pr_number": 7}}));
+        assert_eq!(client.pull_request_number(), Some(7));
+        let client = test_client("", "workflow_dispatch", json!({"inputs": {}}));
+        assert_eq!(client.pull_request_number(), None);
+        for event in ["push", "merge_group"] {
+            let client = test_client("", event, json!({"number": 42}));
+            assert_eq!(client.pull_request_number(), None);
+        }
+    }
+
+    #[test]
+    fn merge_group_range() {
+        let mut server = mockito::Server::new();
+        let mock = server
+            .mock(
+                "GET",
+                format!("/repos/{REPO}/compare/{BEFORE}...{AFTER}").as_str(),
+            )
+            .with_body(DIFF)
+            .create();
+        let client = test_client(
+            &server.url(),
+            "merge_group",
+            json!({"merge_group": {"base_sha": BEFORE, "head_sha": AFTER}}),
+        );
+        let range = client.merge_group_range();
+        assert_eq!(range, Some((BEFORE, AFTER)));
+        let files = client.get_compare_changed_files(
+            REPO,
+            range,
+            AFTER,
+            &["cpp"],
+            &PathSet::default(),
+            &PathSet::default(),
+        );
+        mock.assert();
+        assert_eq!(files.len(), 1);
+    }
+
+    /// Mock the `pulls/42` endpoint: the diff describes 1 file, and the pull request
+    /// changed `changed_files` files.
+    fn mock_pr(server: &mut mockito::Server, changed_files: u64) -> (mockito::Mock, mockito::Mock) {
+        let path = format!("/repos/{REPO}/pulls/42");
+        (
+            server
+                .mock("GET", path.as_str())
+                .match_header("accept", "application/vnd.github.diff")
+                .with_body(DIFF)
+                .create(),
+            server
+                .mock("GET", path.as_str())
+                .match_header("accept", "application/vnd.github.text+json")
+                .with_body(json!({"number": 42, "changed_files": changed_files}).to_string())
+                .create(),
+        )
+    }
+
+    #[test]
+    fn workflow_dispatch_pr_truncated() {
+        let mut server = mockito::Server::new();
+        let (diff_mock, pr_mock) = mock_pr(&mut server, 103);
+        let (page_1, page_2) = mock_pr_files(&mut server);
+        let client = test_client(
+            &server.url(),
+            "workflow_dispatch",
+            json!({"inputs": {"pr-number": "42"}}),
+        );
+        let files = client.get_pr_changed_files(
+            REPO,
+            "42",
+            &["cpp"],
+            &PathSet::default(),
+            &PathSet::default(),
+        );
+        for mock in [&diff_mock, &pr_mock, &page_1, &page_2] {
+            mock.assert();
+        }
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[0].added_lines, vec![2]);
+    }
+
+    #[test]
+    fn workflow_dispatch_pr() {
+        let mut server = mockito::Server::new();
+        let (mock, pr_mock) = mock_pr(&mut server, 1);
+        let client = test_client(
+            &server.url(),
+            "workflow_dispatch",
+            json!({"inputs": {"pr-number": "42"}}),
+        );
+        let pr_number = client.pull_request_number().unwrap();
+        let files = client.get_pr_changed_files(
+            REPO,
+            &pr_number.to_string(),
+            &["cpp"],
+            &PathSet::default(),
+            &PathSet::default(),
+        );
+        mock.assert();
+        pr_mock.assert();
+        assert_eq!(files.len(), 1);
+    }
+
+    const COMMENTS_PATH: &str = "/repos/cpp-linter/test-repo/issues/42/comments";
+    const MARKER: &str = "<!-- cpp linter action -->";
+    const COMMENT: &str = "<!-- cpp linter action -->\n# Cpp-Linter Report";
+    const HUMAN_ID: u64 = 1234;
+
+    fn thread_comment(server_url: &str, id: u64, body: &str, user_id: u64) -> serde_json::Value {
+        json!({
+            "id": id,
+            "url": format!("{server_url}/repos/{REPO}/issues/comments/{id}"),
+            "body": body,
+            "user": {
+                "login": match user_id {
+                    HUMAN_ID => "someone",
+                    41898282 => GITHUB_ACTIONS_LOGIN,
+                    _ => "other-app[bot]",
+                },
+                "id": user_id,
+            }
+        })
+    }
+
+    /// Mock 2 pages of comments: the bot's comments have ids 1 and 3.
+    fn mock_comments(server: &mut mockito::ServerGuard) -> Vec<mockito::Mock> {
+        let url = server.url();
+        let page_1 = json!([
+            thread_comment(&url, 1, COMMENT, 41898282),
+            thread_comment(&url, 2, COMMENT, HUMAN_ID),
+        ]);
+        let page_2 = json!([
+            thread_comment(&url, 3, COMMENT, 41898282),
+            thread_comment(&url, 4, "LGTM", 41898282),
+        ]);
+        vec![
+            server
+                .mock("GET", format!("{COMMENTS_PATH}?per_page=100").as_str())
+                .match_header("authorization", "token test-token")
+                .with_header(
+                    "link",
+                    &format!("<{url}{COMMENTS_PATH}?per_page=100&page=2>; rel=\"next\""),
+                )
+                .with_body(page_1.to_string())
+                .create(),
+            server
+                .mock(
+                    "GET",
+                    format!("{COMMENTS_PATH}?per_page=100&page=2").as_str(),
+                )
+                .match_header("authorization", "token test-token")
+                .with_body(page_2.to_string())
+                .create(),
+        ]
+    }
+
+    fn mock_delete(server: &mut mockito::ServerGuard, id: u64, hits: usize) -> mockito::Mock {
+        server
+            .mock(
+                "DELETE",
+                format!("/repos/{REPO}/issues/comments/{id}").as_str(),
+            )
+            .match_header("authorization", "token test-token")
+            .with_status(204)
+            .expect(hits)
+            .create()
+    }
+
+    #[test]
+    fn comment_create() {
+        let mut server = mockito::Server::new();
+        let list = server
+            .mock("GET", format!("{COMMENTS_PATH}?per_page=100").as_str())
+            .match_header("authorization", "token test-token")
+            .with_body("[]")
+            .create();
+        let post = server
+            .mock("POST", COMMENTS_PATH)
+            .match_header("authorization", "token test-token")
+            .match_body(mockito::Matcher::Json(json!({"body": COMMENT})))
+            .with_status(201)
+            .create();
+        let client = test_client(&server.url(), "pull_request", json!({"number": 42}));
+        let url = format!("{}{COMMENTS_PATH}", server.url());
+        client.update_comment(&url, COMMENT, MARKER, false, false, true);
+        list.assert();
+        post.assert();
+    }
+
+    #[test]
+    fn comment_update() {
+        let mut server = mockito::Server::new();
+        let lists = mock_comments(&mut server);
+        let patch = server
+            .mock("PATCH", format!("/repos/{REPO}/issues/comments/1").as_str())
+            .match_header("authorization", "token test-token")
+            .match_body(mockito::Matcher::Json(json!({"body": COMMENT})))
+            .create();
+        let deleted = mock_delete(&mut server, 3, 1);
+        let kept = [
+            mock_delete(&mut server, 1, 0),
+            mock_delete(&mut server, 2, 0),
+            mock_delete(&mut server, 4, 0),
+        ];
+        let post = server.mock("POST", COMMENTS_PATH).expect(0).create();
+        let client = test_client(&server.url(), "pull_request", json!({"number": 42}));
+        let url = format!("{}{COMMENTS_PATH}", server.url());
+        client.update_comment(&url, COMMENT, MARKER, false, false, true);
+        for mock in lists.iter().chain(kept.iter()) {
+            mock.assert();
+        }
+        patch.assert();
+        deleted.assert();
+        post.assert();
+    }
+
+    #[test]
+    fn comment_replace() {
+        let mut server = mockito::Server::new();
+        let lists = mock_comments(&mut server);
+        let deleted = [
+            mock_delete(&mut server, 1, 1),
+            mock_delete(&mut server, 3, 1),
+        ];
+        let kept = [
+            mock_delete(&mut server, 2, 0),
+            mock_delete(&mut server, 4, 0),
+        ];
+        let post = server
+            .mock("POST", COMMENTS_PATH)
+            .match_header("authorization", "token test-token")
+            .with_status(201)
+            .create();
+        let client = test_client(&server.url(), "pull_request", json!({"number": 42}));
+        let url = format!("{}{COMMENTS_PATH}", server.url());
+        client.update_comment(&url, COMMENT, MARKER, false, false, false);
+        for mock in lists.iter().chain(deleted.iter()).chain(kept.iter()) {
+            mock.assert();
+        }
+        post.assert();
+    }
+
+    #[test]
+    fn comment_delete_lgtm() {
+        let mut server = mockito::Server::new();
+        let lists = mock_comments(&mut server);
+        let deleted = [
+            mock_delete(&mut server, 1, 1),
+            mock_delete(&mut server, 3, 1),
+        ];
+        let post = server.mock("POST", COMMENTS_PATH).expect(0).create();
+        let patch = server
+            .mock("PATCH", mockito::Matcher::Any)
+            .expect(0)
+            .create();
+        let client = test_client(&server.url(), "pull_request", json!({"number": 42}));
+        let url = format!("{}{COMMENTS_PATH}", server.url());
+        client.update_comment(&url, COMMENT, MARKER, true, true, true);
+        for mock in lists.iter().chain(deleted.iter()) {
+            mock.assert();
+        }
+        post.assert();
+        patch.assert();
+    }
+
+    #[test]
+    fn comment_identity_and_key() {
+        let mut server = mockito::Server::new();
+        let user = server
+            .mock("GET", "/user")
+            .match_header("authorization", "token test-token")
+            .with_body(json!({"login": "my-app[bot]", "id": 99}).to_string())
+            .create();
+        let url = server.url();
+        let keyed = "<!-- cpp linter action: linux -->\n# Cpp-Linter Report";
+        let comments = json!([
+            thread_comment(&url, 1, COMMENT, 99),
+            thread_comment(&url, 2, keyed, 41898282),
+            thread_comment(&url, 3, keyed, 99),
+            thread_comment(&url, 4, "<!-- cpp linter action: linux-arm -->", 99),
+        ]);
+        let list = server
+            .mock("GET", format!("{COMMENTS_PATH}?per_page=100").as_str())
+            .with_body(comments.to_string())
+            .create();
+        let deleted = mock_delete(&mut server, 3, 1);
+        let kept = [
+            mock_delete(&mut server, 1, 0),
+            mock_delete(&mut server, 2, 0),
+            mock_delete(&mut server, 4, 0),
+        ];
+        let post = server.mock("POST", COMMENTS_PATH).with_status(201).create();
+        let client = test_client(&server.url(), "pull_request", json!({"number": 42}));
+        let comments_url = format!("{url}{COMMENTS_PATH}");
+        let marker = make_comment_marker("linux");
+        client.update_comment(&comments_url, keyed, &marker, false, false, false);
+        for mock in [&user, &list, &deleted, &post]
+            .into_iter()
+            .chain(kept.iter())
+        {
+            mock.assert();
+        }
+    }
+
+    #[test]
+    fn comment_app_token() {
+        let mut server = mockito::Server::new();
+        let user = server
+            .mock("GET", "/user")
+            .with_status(403)
+            .with_body(json!({"message": "Resource not accessible by integration"}).to_string())
+            .expect(1)
+            .create();
+        let url = server.url();
+        // the token's user is unknown, so only github-actions[bot]'s comments are ours
+        let comments = json!([
+            thread_comment(&url, 1, COMMENT, 41898282),
+            thread_comment(&url, 2, COMMENT, HUMAN_ID),
+            thread_comment(&url, 3, COMMENT, 77),
+        ]);
+        let list = server
+            .mock("GET", format!("{COMMENTS_PATH}?per_page=100").as_str())
+            .with_body(comments.to_string())
+            .create();
+        let deleted = mock_delete(&mut server, 1, 1);
+        let kept = mock_delete(&mut server, 2, 0);
+        let other_app = mock_delete(&mut server, 3, 0);
+        let post = server.mock("POST", COMMENTS_PATH).with_status(201).create();
+        let client = test_client(&server.url(), "pull_request", json!({"number": 42}));
+        let comments_url = format!("{url}{COMMENTS_PATH}");
+        client.update_comment(&comments_url, COMMENT, MARKER, false, false, false);
+        for mock in [&user, &list, &deleted, &kept, &other_app, &post] {
+            mock.assert();
+        }
+    }
+
+    #[test]
+    fn workflow_data() {
+        assert_eq!(
+            escape_workflow_data("100% failed\r\nsee %0A"),
+            "100%25 failed%0D%0Asee %250A"
+        );
+    }
+
+    #[test]
+    fn workflow_annotations() {
+        assert_eq!(
+            escape_workflow_property("src/a,b:c%.cpp\n"),
+            "src/a%2Cb%3Ac%25.cpp%0A"
+        );
+        let name = "src/a,b.cpp";
+        let files = vec![FileObj::new(PathBuf::from(name))];
+        let errors = vec![ToolError {
+            tool: String::from("clang-tidy"),
+            filename: name.to_string(),
+            message: String::from("clang-tidy could not process the file.\n50% done"),
+        }];
+        let format_advice = vec![FormatAdvice {
+            replacements: vec![Replacement {
+                offset: 0,
+                length: 1,
+                value: None,
+                line: Some(3),
+                cols: Some(1),
+            }],
+            patch: None,
+        }];
+        let tidy_advice = vec![vec![TidyNotification {
+            filename: name.to_string(),
+            line: 2,
+            cols: 4,
+            severity: String::from("note"),
+            rationale: String::from("some rationale"),
+            diagnostic: String::from("readability-demo"),
+            suggestion: vec![],
+            related: vec![],
+        }]];
+        let commands =
+            make_workflow_annotations(&files, &format_advice, &tidy_advice, &errors, "llvm");
+        assert_eq!(
+            commands,
+            vec![
+                "::error file=src/a%2Cb.cpp,title=clang-tidy failed on src/a%2Cb.cpp::\
+                 clang-tidy could not process the file.%0A50%25 done",
+                "::notice file=src/a%2Cb.cpp,title=Run clang-format on src/a%2Cb.cpp::\
+                 File src/a,b.cpp does not conform to LLVM style guidelines. (lines 3)",
+                "::notice file=src/a%2Cb.cpp,line=2,title=src/a%2Cb.cpp%3A2%3A4 [readability-demo]::\
+                 some rationale",
+            ]
+        );
+    }
+
+    #[test]
+    fn check_conclusion() {
+        assert_eq!(get_check_conclusion(0, 1), "success");
+        assert_eq!(get_check_conclusion(1, 1), "failure");
+        assert_eq!(get_check_conclusion(2, 3), "neutral");
+        assert_eq!(get_check_conclusion(3, 3), "failure");
+        assert_eq!(get_check_conclusion(100, 0), "neutral");
+    }
+
+    #[test]
+    fn tool_error_annotations() {
+        let errors = vec![ToolError {
+            tool: String::from("clang-tidy"),
+            filename: String::from("src/demo.cpp"),
+            message: String::from(
+                "clang-tidy reported compilation errors.\nFound compiler error(s).",
+            ),
+        }];
+        let annotations = make_check_annotations(&[], &[], &[], &errors, "llvm");
+        assert_eq!(annotations.len(), 1);
+        assert_eq!(annotations[0].annotation_level, "failure");
+        assert_eq!(annotations[0].title, "clang-tidy failed on src/demo.cpp");
+        assert_eq!(
+            annotations[0].message,
+            "clang-tidy reported compilation errors."
+        );
+        assert_eq!(
+            annotations[0].raw_details.as_deref(),
+            Some("Found compiler error(s).")
+        );
+    }
+
+    /// Count the annotations in a check run request's body.
+    fn count_annotations(request: &mockito::Request) -> usize {
+        let body: serde_json::Value = serde_json::from_slice(request.body().unwrap()).unwrap();
+        body["output"]["annotations"].as_array().unwrap().len()
+    }
+
+    #[test]
+    fn check_run_batches() {
+        let files = vec![FileObj::new(PathBuf::from("src/demo.cpp"))];
+        let notes = (1..=60)
+            .map(|line| TidyNotification {
+                filename: String::from("src/demo.cpp"),
+                line,
+                cols: 1,
+                severity: String::from(if line % 2 == 0 { "warning" } else { "error" }),
+                rationale: String::from(" some concern "),
+                diagnostic: String::from("bugprone-demo"),
+                suggestion: vec![String::from("int a;"), String::from("^")],
+                related: vec![],
+            })
+            .collect::<Vec<_>>();
+        let annotations = make_check_annotations(&files, &[], &[notes], &[], "llvm");
+        assert_eq!(annotations.len(), 60);
+        assert_eq!(annotations[0].annotation_level, "failure");
+        assert_eq!(annotations[1].annotation_level, "warning");
+        assert_eq!(annotations[0].message, "some concern");
+        assert_eq!(annotations[0].raw_details.as_deref(), Some("int a;\n^"));
+
+        let mut server = mockito::Server::new();
+        let head_sha = "3333333333333333333333333333333333333333";
+        let create = server
+            .mock("POST", format!("/repos/{REPO}/check-runs").as_str())
+            .match_header("authorization", "token test-token")
+            .match_body(mockito::Matcher::PartialJson(json!({
+                "name": "cpp-linter (linux)",
+                "head_sha": head_sha,
+                "status": "completed",
+                "conclusion": "neutral",
+                "output": {"summary": "report"},
+            })))
+            .match_request(|request| count_annotations(request) == 50)
+            .with_status(201)
+            .with_body(json!({"id": 7}).to_string())
+            .create();
+        let update = server
+            .mock("PATCH", format!("/repos/{REPO}/check-runs/7").as_str())
+            .match_header("authorization", "token test-token")
+            .match_request(|request| count_annotations(request) == 10)
+            .create();
+        let client = test_client(
+            &server.url(),
+            "pull_request",
+            json!({"number": 42, "pull_request": {"head": {"sha": head_sha}}}),
+        );
+        let user_inputs = FeedbackInput {
+            comment_key: String::from("linux"),
+            check_run: true,
+            check_failure_threshold: 100,
+            ..Default::default()
+        };
+        client.post_check_run("report", &annotations, 60, &user_inputs);
+        create.assert();
+        update.assert();
+    }
+}
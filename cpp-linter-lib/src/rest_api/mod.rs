@@ -3,24 +3,450 @@
 //!
 //! Currently, only Github is supported.
 
-use std::path::PathBuf;
+use std::fmt;
+use std::path::Path;
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 // non-std crates
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use reqwest::blocking::{Client, RequestBuilder, Response};
 use reqwest::header::{HeaderMap, HeaderValue};
+use reqwest::{Method, StatusCode};
 
 // project specific modules/crates
 pub mod github_api;
-use crate::clang_tools::{clang_format::FormatAdvice, clang_tidy::TidyNotification};
-use crate::common_fs::FileObj;
+pub mod report;
+use crate::clang_tools::{clang_format::FormatAdvice, clang_tidy::TidyNotification, ToolError};
+use crate::common_fs::{FileObj, PathSet};
+use report::{fit_report, make_report, ReportContext, DEFAULT_TEMPLATE};
+
+/// Create the hidden marker that prefixes all comments posted by cpp-linter.
+///
+/// This is used to find outdated comments (from previous runs) in a thread. A non-empty
+/// `comment_key` is included in the marker, so that multiple independent reports (eg.
+/// from jobs on different platforms) can coexist in the same thread.
+pub fn make_comment_marker(comment_key: &str) -> String {
+    if comment_key.is_empty() {
+        String::from("<!-- cpp linter action -->")
+    } else {
+        format!("<!-- cpp linter action: {comment_key} -->")
+    }
+}
+
+/// Get the URL of the next page of a paginated REST API response's `headers`.
+///
+/// This parses the `Link` header (see [RFC 8288](https://www.rfc-editor.org/rfc/rfc8288))
+/// for a link with `rel="next"`. Returns [`None`] if there is no next page.
+pub fn get_next_page_url(headers: &HeaderMap) -> Option<String> {
+    let links = headers.get("link")?.to_str().ok()?;
+    links.split(',').find_map(|link| {
+        let mut parts = link.split(';');
+        let url = parts.next()?.trim();
+        let is_next = parts.any(|param| {
+            let param = param.trim().replace(' ', "");
+            param == "rel=\"next\"" || param == "rel=next"
+        });
+        if is_next && url.starts_with('<') && url.ends_with('>') {
+            Some(url[1..url.len() - 1].to_string())
+        } else {
+            None
+        }
+    })
+}
+
+/// Settings that control how REST API requests are retried (see [`send_api_request()`]).
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    /// The maximum number of times a request is retried.
+    pub max_retries: u8,
+
+    /// The delay before the first retry. This is doubled for each subsequent retry.
+    pub base_delay: Duration,
+
+    /// The longest delay to wait before a retry.
+    ///
+    /// If a rate limit will not reset within this duration, then the request fails
+    /// immediately instead.
+    pub max_delay: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy {
+            max_retries: 5,
+            base_delay: Duration::from_secs(1),
+            max_delay: Duration::from_secs(120),
+        }
+    }
+}
+
+/// Describes why a REST API request ultimately failed.
+#[derive(Debug)]
+pub enum RestApiError {
+    /// The request could not be sent or no response was received.
+    Request(reqwest::Error),
+
+    /// The server responded with an unsuccessful status after all retries.
+    Status(StatusCode),
+
+    /// The rate limit was exceeded, and it will not reset within an acceptable time.
+    RateLimited(Duration),
+}
+
+impl fmt::Display for RestApiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RestApiError::Request(e) => write!(f, "request failed: {e}"),
+            RestApiError::Status(status) => write!(f, "got {status} response"),
+            RestApiError::RateLimited(wait) => write!(
+                f,
+                "rate limit exceeded; it resets in {} seconds",
+                wait.as_secs()
+            ),
+        }
+    }
+}
+
+impl std::error::Error for RestApiError {}
+
+/// The shortest delay before retrying a request that exceeded a rate limit, when the
+/// response does not say when the rate limit resets.
+///
+/// GitHub recommends waiting at least one minute in this case.
+const MIN_RATE_LIMIT_DELAY: Duration = Duration::from_secs(60);
+
+/// Determine how long to wait before retrying a request that got a rate limited
+/// `response`.
+///
+/// This honors the `Retry-After` header (for secondary rate limits) and the
+/// `x-ratelimit-reset` header (for primary rate limits). If neither is given, then the
+/// exponential `backoff` is used (but no less than [`MIN_RATE_LIMIT_DELAY`]). Returns
+/// [`None`] if the `response` does not describe a rate limit.
+fn get_rate_limit_delay(response: &Response, backoff: Duration) -> Option<Duration> {
+    let headers = response.headers();
+    let header_as_u64 = |name: &str| {
+        headers
+            .get(name)
+            .and_then(|val| val.to_str().ok())
+            .and_then(|val| val.trim().parse::<u64>().ok())
+    };
+    if let Some(secs) = header_as_u64("retry-after") {
+        return Some(Duration::from_secs(secs));
+    }
+    let is_exhausted = header_as_u64("x-ratelimit-remaining") == Some(0);
+    if is_exhausted {
+        if let Some(reset) = header_as_u64("x-ratelimit-reset") {
+            let now = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs();
+            return Some(Duration::from_secs(reset.saturating_sub(now)));
+        }
+    }
+    if is_exhausted || response.status() == StatusCode::TOO_MANY_REQUESTS {
+        return Some(backoff.max(MIN_RATE_LIMIT_DELAY));
+    }
+    None
+}
+
+/// Send a REST API `request` using the given `client`.
+///
+/// Requests are retried according to the given `policy` when
+///
+/// - the request could not be sent (eg. a connection error),
+/// - the response has a server error status (eg. `502 Bad Gateway`), or
+/// - the response indicates a primary or secondary rate limit.
+///
+/// Requests that are not idempotent (`POST` and `PATCH`) are only retried for rate
+/// limits, because the server might have already processed them otherwise.
+///
+/// Other unsuccessful responses (eg. `404 Not Found`) are returned as is, so the
+/// caller can decide how to handle them. The remaining rate limit quota is logged (at
+/// debug level) for each response.
+pub fn send_api_request(
+    client: &Client,
+    request: RequestBuilder,
+    policy: &RetryPolicy,
+) -> Result<Response, RestApiError> {
+    let request = request.build().map_err(RestApiError::Request)?;
+    let is_idempotent = !matches!(*request.method(), Method::POST | Method::PATCH);
+    let mut attempt = 0;
+    loop {
+        let backoff = policy
+            .base_delay
+            .saturating_mul(2_u32.saturating_pow(attempt as u32))
+            .min(policy.max_delay);
+        let req = request
+            .try_clone()
+            .expect("REST API requests should not use a streamed body");
+        let delay = match client.execute(req) {
+            Ok(response) => {
+                if let Some(remaining) = response.headers().get("x-ratelimit-remaining") {
+                    log::debug!(
+                        "{} REST API requests remaining",
+                        remaining.to_str().unwrap_or("unknown")
+                    );
+                }
+                let status = response.status();
+                let is_rate_limit = matches!(
+                    status,
+                    StatusCode::FORBIDDEN | StatusCode::TOO_MANY_REQUESTS
+                );
+                let delay = if is_rate_limit {
+                    get_rate_limit_delay(&response, backoff)
+                } else if status.is_server_error() && is_idempotent {
+                    Some(backoff)
+                } else {
+                    None
+                };
+                match delay {
+                    None => return Ok(response),
+                    Some(delay) => {
+                        if attempt >= policy.max_retries {
+                            return Err(RestApiError::Status(status));
+                        }
+                        if delay > policy.max_delay {
+                            return Err(RestApiError::RateLimited(delay));
+                        }
+                        log::warn!(
+                            "Got {status} response from {}; retrying in {} seconds",
+                            request.url().path(),
+                            delay.as_secs()
+                        );
+                        delay
+                    }
+                }
+            }
+            Err(e) => {
+                if attempt >= policy.max_retries || !is_idempotent {
+                    return Err(RestApiError::Request(e));
+                }
+                log::warn!("{e}; retrying in {} seconds", backoff.as_secs());
+                backoff
+            }
+        };
+        thread::sleep(delay);
+        attempt += 1;
+    }
+}
+
+/// The number of clang-tidy concerns per severity (see [`FailurePolicy::count()`]).
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct TidyCounts {
+    /// The number of concerns with `error` severity.
+    pub errors: u32,
+
+    /// The number of concerns with `warning` severity.
+    pub warnings: u32,
+
+    /// The number of concerns with `note` (or any other) severity.
+    pub notes: u32,
+
+    /// The number of concerns that count as failures.
+    pub failed: u32,
+}
+
+/// A structure to hold the CLI options that decide which clang-tidy concerns count as
+/// failures (`tidy-checks-failed`).
+#[derive(Debug, Clone)]
+pub struct FailurePolicy {
+    /// The `--fail-severity` option.
+    pub fail_severity: String,
+
+    /// The compiled glob patterns of the `--blocking-checks` option (if any).
+    pub blocking_checks: Option<GlobSet>,
+
+    /// The `--max-warnings` option.
+    pub max_warnings: Option<u32>,
+}
+
+impl Default for FailurePolicy {
+    fn default() -> Self {
+        FailurePolicy {
+            fail_severity: String::from("note"),
+            blocking_checks: None,
+            max_warnings: None,
+        }
+    }
+}
+
+/// Get the rank of a clang-tidy `severity` (a greater rank is more severe).
+fn severity_rank(severity: &str) -> u8 {
+    match severity {
+        "error" => 2,
+        "warning" => 1,
+        _ => 0,
+    }
+}
+
+impl FailurePolicy {
+    /// Create a policy from the given CLI options.
+    ///
+    /// The `blocking_checks` (if any) is a comma-separated list of glob patterns that
+    /// describe diagnostic names.
+    ///
+    /// Returns an error (that names the invalid CLI option) if the `fail_severity` is
+    /// unknown or the `blocking_checks` has an invalid glob pattern.
+    pub fn new(
+        fail_severity: &str,
+        blocking_checks: Option<&str>,
+        max_warnings: Option<u32>,
+    ) -> Result<Self, String> {
+        if !["note", "warning", "error"].contains(&fail_severity) {
+            return Err(format!(
+                "Invalid --fail-severity {fail_severity:?}; expected note, warning or error"
+            ));
+        }
+        let blocking_checks = match blocking_checks {
+            Some(patterns) => {
+                let mut builder = GlobSetBuilder::new();
+                for pattern in patterns
+                    .split(',')
+                    .map(|p| p.trim())
+                    .filter(|p| !p.is_empty())
+                {
+                    builder.add(
+                        Glob::new(pattern)
+                            .map_err(|e| format!("Invalid --blocking-checks pattern: {e}"))?,
+                    );
+                }
+                Some(
+                    builder
+                        .build()
+                        .map_err(|e| format!("Invalid --blocking-checks: {e}"))?,
+                )
+            }
+            None => None,
+        };
+        Ok(FailurePolicy {
+            fail_severity: fail_severity.to_string(),
+            blocking_checks,
+            max_warnings,
+        })
+    }
+
+    /// Is the given clang-tidy concern blocking?
+    ///
+    /// A concern is blocking if its `severity` is at least the
+    /// [`FailurePolicy::fail_severity`] and its `diagnostic` matches the
+    /// [`FailurePolicy::blocking_checks`] (if any).
+    pub fn is_blocking(&self, severity: &str, diagnostic: &str) -> bool {
+        let is_blocking_check = match &self.blocking_checks {
+            Some(checks) => checks.is_match(diagnostic),
+            None => true,
+        };
+        severity_rank(severity) >= severity_rank(&self.fail_severity) && is_blocking_check
+    }
+
+    /// Count the concerns in `tidy_advice` about the given set of `files`.
+    ///
+    /// Blocking concerns (see [`FailurePolicy::is_blocking()`]) count as failures.
+    /// Other concerns are informational, unless there are more of them than the
+    /// [`FailurePolicy::max_warnings`] budget allows (then they all count as failures).
+    pub fn count(&self, files: &[FileObj], tidy_advice: &[Vec<TidyNotification>]) -> TidyCounts {
+        let mut counts = TidyCounts::default();
+        let mut informational = 0;
+        for (index, notes) in tidy_advice.iter().enumerate() {
+            for note in notes {
+                if Path::new(&note.filename) != files[index].name {
+                    // not shown in the report
+                    continue;
+                }
+                match severity_rank(&note.severity) {
+                    2 => counts.errors += 1,
+                    1 => counts.warnings += 1,
+                    _ => counts.notes += 1,
+                }
+                if self.is_blocking(&note.severity, &note.diagnostic) {
+                    counts.failed += 1;
+                } else {
+                    informational += 1;
+                }
+            }
+        }
+        if self
+            .max_warnings
+            .is_some_and(|max_warnings| informational > max_warnings)
+        {
+            counts.failed += informational;
+        }
+        counts
+    }
+}
+
+/// A structure to hold the CLI options that control how feedback is posted (see
+/// [`RestApiClient::post_feedback()`]).
+#[derive(Debug, Clone)]
+pub struct FeedbackInput {
+    /// The `--thread-comments` option.
+    pub thread_comments: String,
+
+    /// The `--no-lgtm` option.
+    pub no_lgtm: bool,
+
+    /// The `--step-summary` option.
+    pub step_summary: bool,
+
+    /// The `--file-annotations` option.
+    pub file_annotations: bool,
+
+    /// The `--style` option.
+    pub style: String,
+
+    /// The `--comment-key` option.
+    pub comment_key: String,
+
+    /// The `--check-run` option.
+    pub check_run: bool,
+
+    /// The `--check-failure-threshold` option.
+    pub check_failure_threshold: u32,
+
+    /// The content of the `--comment-template` file (if any).
+    pub comment_template: Option<String>,
+
+    /// The options that decide which clang-tidy concerns count as failures.
+    pub failure_policy: FailurePolicy,
+
+    /// The `--fail-on-tool-errors` option.
+    pub fail_on_tool_errors: bool,
+}
+
+impl Default for FeedbackInput {
+    fn default() -> Self {
+        FeedbackInput {
+            thread_comments: String::from("false"),
+            no_lgtm: true,
+            step_summary: false,
+            file_annotations: true,
+            style: String::from("llvm"),
+            comment_key: String::new(),
+            check_run: false,
+            check_failure_threshold: 1,
+            comment_template: None,
+            failure_policy: FailurePolicy::default(),
+            fail_on_tool_errors: true,
+        }
+    }
+}
+
+/// The maximum length (in bytes) of a comment's body.
+///
+/// GitHub rejects thread comments (and check run summaries) that are longer than this.
+pub const MAX_COMMENT_LEN: usize = 65535;
 
 /// A custom trait that templates necessary functionality with a Git server's REST API.
 pub trait RestApiClient {
     /// A way to set output variables specific to cpp_linter executions in CI.
+    ///
+    /// The `tidy_counts` (if given) are also set as output variables per severity.
     fn set_exit_code(
         &self,
         checks_failed: i32,
         format_checks_failed: Option<i32>,
         tidy_checks_failed: Option<i32>,
+        tidy_counts: Option<&TidyCounts>,
     ) -> i32;
 
     /// A convenience method to create the headers attached to all REST API calls.
@@ -37,79 +463,70 @@ pub trait RestApiClient {
     fn get_list_of_changed_files(
         &self,
         extensions: &[&str],
-        ignored: &[String],
-        not_ignored: &[String],
+        ignored: &PathSet,
+        not_ignored: &PathSet,
     ) -> Vec<FileObj>;
 
+    /// Get the URL of a page that shows the full report (eg. the CI run's step
+    /// summary).
+    ///
+    /// This is linked in comments that were truncated (see
+    /// [`RestApiClient::make_comment()`]). The default implementation returns [`None`].
+    fn get_full_report_url(&self) -> Option<String> {
+        None
+    }
+
     /// Makes a comment in MarkDown syntax based on the concerns in `format_advice` and
-    /// `tidy_advice` about the given set of `files`.
+    /// `tidy_advice` about the given set of `files`, and the `tool_errors` (if any).
+    ///
+    /// The comment is rendered from the [`FeedbackInput::comment_template`] (or the
+    /// [`DEFAULT_TEMPLATE`]), and it is prefixed with a marker made from the
+    /// [`FeedbackInput::comment_key`] (see [`make_comment_marker()`]).
+    ///
+    /// If a `max_len` is given, then the comment is truncated to fit within that many
+    /// bytes. Concerns are omitted from the end of the comment (with a note about how
+    /// many are not shown), and a link to the full report is added (see
+    /// [`RestApiClient::get_full_report_url()`]).
     ///
     /// This method has a default definition and should not need to be redefined by
     /// implementors.
     ///
-    /// Returns the markdown comment as a string as well as the total count of
-    /// `format_checks_failed` and `tidy_checks_failed` (in respective order).
+    /// Returns the markdown comment and the full (not truncated) report as strings as
+    /// well as the total count of `format_checks_failed` and `tidy_checks_failed` (in
+    /// respective order). Only the clang-tidy concerns that count as failures (see
+    /// [`FeedbackInput::failure_policy`]) are included in `tidy_checks_failed`.
     fn make_comment(
         &self,
         files: &[FileObj],
         format_advice: &[FormatAdvice],
         tidy_advice: &[Vec<TidyNotification>],
-    ) -> (String, i32, i32) {
-        let mut comment = String::from("<!-- cpp linter action -->\n# Cpp-Linter Report ");
-        let mut format_checks_failed = 0;
-        let mut tidy_checks_failed = 0;
-        let mut format_comment = String::new();
-        for (index, fmt_advice) in format_advice.iter().enumerate() {
-            if !fmt_advice.replacements.is_empty() {
-                format_comment.push_str(
-                    &format!(
-                        "- {}\n",
-                        files[index].name.to_string_lossy().replace('\\', "/")
-                    )
-                    .to_string(),
-                );
-                format_checks_failed += 1;
-            }
-        }
-
-        let mut tidy_comment = String::new();
-        for (index, tidy_notes) in tidy_advice.iter().enumerate() {
-            for tidy_note in tidy_notes {
-                let file_path = PathBuf::from(&tidy_note.filename);
-                if file_path == files[index].name {
-                    tidy_comment.push_str(&format!("- {}\n\n", tidy_note.filename));
-                    tidy_comment.push_str(&format!(
-                        "   <strong>{filename}:{line}:{cols}:</strong> {severity}: [{diagnostic}]\n   > {rationale}\n{concerned_code}",
-                        filename = tidy_note.filename,
-                        line = tidy_note.line,
-                        cols = tidy_note.cols,
-                        severity = tidy_note.severity,
-                        diagnostic = tidy_note.diagnostic,
-                        rationale = tidy_note.rationale,
-                        concerned_code = if tidy_note.suggestion.is_empty() {String::from("")} else {
-                            format!("\n   ```{ext}\n   {suggestion}\n   ```\n",
-                                ext = file_path.extension().expect("file extension was not determined").to_string_lossy(),
-                                suggestion = tidy_note.suggestion.join("\n    "),
-                            ).to_string()
-                        },
-                    ).to_string());
-                    tidy_checks_failed += 1;
-                }
-            }
-        }
-        if format_checks_failed > 0 || tidy_checks_failed > 0 {
-            comment.push_str(":warning:\nSome files did not pass the configured checks!\n");
-            if format_checks_failed > 0 {
-                comment.push_str(&format!("\n<details><summary>clang-format reports: <strong>{} file(s) not formatted</strong></summary>\n\n{}\n</details>", format_checks_failed, &format_comment));
-            }
-            if tidy_checks_failed > 0 {
-                comment.push_str(&format!("\n<details><summary>clang-tidy reports: <strong>{} concern(s)</strong></summary>\n\n{}\n</details>", tidy_checks_failed, tidy_comment));
-            }
-        } else {
-            comment.push_str(":heavy_check_mark:\nNo problems need attention.");
-        }
-        comment.push_str("\n\nHave any feedback or feature suggestions? [Share it here.](https://github.com/cpp-linter/cpp-linter-action/issues)");
-        (comment, format_checks_failed, tidy_checks_failed)
+        tool_errors: &[ToolError],
+        user_inputs: &FeedbackInput,
+        max_len: Option<usize>,
+    ) -> (String, String, i32, i32) {
+        let context = ReportContext::new(
+            files,
+            format_advice,
+            tidy_advice,
+            tool_errors,
+            user_inputs,
+            self.get_full_report_url(),
+        );
+        let template = user_inputs
+            .comment_template
+            .as_deref()
+            .unwrap_or(DEFAULT_TEMPLATE);
+        let full_report = make_report(template, &context, None);
+        let comment = match max_len {
+            Some(max_len) => fit_report(&full_report, template, &context, max_len),
+            None => full_report.clone(),
+        };
+        (
+            comment,
+            full_report,
+            context.format_checks_failed as i32,
+            context.tidy_checks_failed as i32,
+        )
     }
 
     /// A way to post feedback in the form of `thread_comments`, `file_annotations`, and
@@ -119,19 +536,484 @@ pub trait RestApiClient {
     /// `list_source_files()`.
     ///
     /// The `format_advice` and `tidy_advice` should be a result of parsing output from
-    /// clang-format and clang-tidy (see `capture_clang_tools_output()`).
+    /// clang-format and clang-tidy (see `capture_clang_tools_output()`). The
+    /// `tool_errors` describe the files that clang-format or clang-tidy failed to
+    /// analyze.
+    ///
+    /// The `user_inputs` correspond to CLI arguments.
     ///
-    /// All other parameters correspond to CLI arguments.
-    #[allow(clippy::too_many_arguments)]
+    /// Returns the total count of `checks_failed` (see [`RestApiClient::set_exit_code()`]).
     fn post_feedback(
         &self,
         files: &[FileObj],
         format_advice: &[FormatAdvice],
         tidy_advice: &[Vec<TidyNotification>],
-        thread_comments: &str,
-        no_lgtm: bool,
-        step_summary: bool,
-        file_annotations: bool,
-        style: &str,
-    );
+        tool_errors: &[ToolError],
+        user_inputs: &FeedbackInput,
+    ) -> i32;
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::PathBuf;
+    use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+    use reqwest::blocking::Client;
+    use reqwest::header::{HeaderMap, HeaderValue};
+    use reqwest::StatusCode;
+
+    use super::{
+        get_next_page_url, make_comment_marker, send_api_request, FailurePolicy, FeedbackInput,
+        RestApiClient, RestApiError, RetryPolicy, TidyCounts,
+    };
+    use crate::clang_tools::clang_format::{FormatAdvice, Replacement};
+    use crate::clang_tools::clang_tidy::TidyNotification;
+    use crate::clang_tools::ToolError;
+    use crate::common_fs::{FileObj, PathSet};
+
+    fn test_policy() -> RetryPolicy {
+        RetryPolicy {
+            max_retries: 2,
+            base_delay: Duration::ZERO,
+            max_delay: Duration::from_secs(1),
+        }
+    }
+
+    #[test]
+    fn retry_server_error() {
+        let mut server = mockito::Server::new();
+        let failed = server
+            .mock("GET", "/data")
+            .with_status(502)
+            .expect(1)
+            .create();
+        let passed = server
+            .mock("GET", "/data")
+            .with_status(200)
+            .with_header("x-ratelimit-remaining", "42")
+            .with_body("ok")
+            .create();
+        let client = Client::new();
+        let response = send_api_request(
+            &client,
+            client.get(format!("{}/data", server.url())),
+            &test_policy(),
+        )
+        .unwrap();
+        assert_eq!(response.text().unwrap(), "ok");
+        failed.assert();
+        passed.assert();
+    }
+
+    #[test]
+    fn retries_exhausted() {
+        let mut server = mockito::Server::new();
+        let failed = server
+            .mock("GET", "/data")
+            .with_status(503)
+            .expect(3)
+            .create();
+        let client = Client::new();
+        let result = send_api_request(
+            &client,
+            client.get(format!("{}/data", server.url())),
+            &test_policy(),
+        );
+        assert!(matches!(
+            result,
+            Err(RestApiError::Status(StatusCode::SERVICE_UNAVAILABLE))
+        ));
+        failed.assert();
+    }
+
+    #[test]
+    fn no_retry_client_error() {
+        let mut server = mockito::Server::new();
+        let missing = server
+            .mock("GET", "/data")
+            .with_status(404)
+            .expect(1)
+            .create();
+        let client = Client::new();
+        let response = send_api_request(
+            &client,
+            client.get(format!("{}/data", server.url())),
+            &test_policy(),
+        )
+        .unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        missing.assert();
+    }
+
+    #[test]
+    fn secondary_rate_limit() {
+        let mut server = mockito::Server::new();
+        let limited = server
+            .mock("GET", "/data")
+            .with_status(403)
+            .with_header("retry-after", "0")
+            .expect(1)
+            .create();
+        let passed = server.mock("GET", "/data").with_status(200).create();
+        let client = Client::new();
+        let response = send_api_request(
+            &client,
+            client.get(format!("{}/data", server.url())),
+            &test_policy(),
+        )
+        .unwrap();
+        assert!(response.status().is_success());
+        limited.assert();
+        passed.assert();
+    }
+
+    #[test]
+    fn primary_rate_limit() {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+        let mut server = mockito::Server::new();
+        let limited = server
+            .mock("GET", "/reset")
+            .with_status(403)
+            .with_header("x-ratelimit-remaining", "0")
+            .with_header("x-ratelimit-reset", &now.to_string())
+            .expect(1)
+            .create();
+        let passed = server.mock("GET", "/reset").with_status(200).create();
+        let exceeded = server
+            .mock("GET", "/wait")
+            .with_status(429)
+            .with_header("x-ratelimit-remaining", "0")
+            .with_header("x-ratelimit-reset", &(now + 3600).to_string())
+            .expect(1)
+            .create();
+        let client = Client::new();
+        let response = send_api_request(
+            &client,
+            client.get(format!("{}/reset", server.url())),
+            &test_policy(),
+        )
+        .unwrap();
+        assert!(response.status().is_success());
+        let result = send_api_request(
+            &client,
+            client.get(format!("{}/wait", server.url())),
+            &test_policy(),
+        );
+        let err = result.unwrap_err();
+        assert!(matches!(err, RestApiError::RateLimited(_)));
+        assert!(err.to_string().starts_with("rate limit exceeded"));
+        limited.assert();
+        passed.assert();
+        exceeded.assert();
+    }
+
+    #[test]
+    fn rate_limit_without_reset() {
+        let mut server = mockito::Server::new();
+        let limited = server
+            .mock("GET", "/data")
+            .with_status(429)
+            .expect(1)
+            .create();
+        let client = Client::new();
+        let result = send_api_request(
+            &client,
+            client.get(format!("{}/data", server.url())),
+            &test_policy(),
+        );
+        // the minimum delay exceeds the policy's max_delay
+        assert!(matches!(
+            result,
+            Err(RestApiError::RateLimited(delay)) if delay >= Duration::from_secs(60)
+        ));
+        limited.assert();
+    }
+
+    #[test]
+    fn no_retry_post_server_error() {
+        let mut server = mockito::Server::new();
+        let failed = server
+            .mock("POST", "/data")
+            .with_status(502)
+            .expect(1)
+            .create();
+        let limited = server
+            .mock("PATCH", "/data")
+            .with_status(429)
+            .with_header("retry-after", "0")
+            .expect(1)
+            .create();
+        let passed = server.mock("PATCH", "/data").with_status(200).create();
+        let client = Client::new();
+        let response = send_api_request(
+            &client,
+            client.post(format!("{}/data", server.url())),
+            &test_policy(),
+        )
+        .unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_GATEWAY);
+        let response = send_api_request(
+            &client,
+            client.patch(format!("{}/data", server.url())),
+            &test_policy(),
+        )
+        .unwrap();
+        assert!(response.status().is_success());
+        failed.assert();
+        limited.assert();
+        passed.assert();
+    }
+
+    #[test]
+    fn connection_error() {
+        let client = Client::new();
+        // nothing should be listening on this port
+        let result = send_api_request(
+            &client,
+            client.get("http://127.0.0.1:1/data"),
+            &test_policy(),
+        );
+        assert!(matches!(result, Err(RestApiError::Request(_))));
+    }
+
+    #[test]
+    fn next_page_link() {
+        let mut headers = HeaderMap::new();
+        assert_eq!(get_next_page_url(&headers), None);
+        headers.insert(
+            "link",
+            HeaderValue::from_static(
+                "<https://api.github.com/repos/a/b/issues/1/comments?page=1>; rel=\"prev\", \
+                 <https://api.github.com/repos/a/b/issues/1/comments?page=3>; rel=\"next\", \
+                 <https://api.github.com/repos/a/b/issues/1/comments?page=5>; rel=\"last\"",
+            ),
+        );
+        assert_eq!(
+            get_next_page_url(&headers).unwrap(),
+            "https://api.github.com/repos/a/b/issues/1/comments?page=3"
+        );
+        headers.insert(
+            "link",
+            HeaderValue::from_static(
+                "<https://api.github.com/repos/a/b/issues/1/comments?page=4>; rel=\"prev\"",
+            ),
+        );
+        assert_eq!(get_next_page_url(&headers), None);
+    }
+
+    #[test]
+    fn comment_markers() {
+        assert_eq!(make_comment_marker(""), "<!-- cpp linter action -->");
+        let linux = make_comment_marker("linux");
+        assert_eq!(linux, "<!-- cpp linter action: linux -->");
+        let lin = make_comment_marker("lin");
+        assert!(!linux.starts_with(&lin));
+        assert!(!linux.starts_with(&make_comment_marker("")));
+    }
+
+    /// A client that only uses the default trait implementations.
+    struct TestClient;
+
+    impl RestApiClient for TestClient {
+        fn set_exit_code(
+            &self,
+            checks_failed: i32,
+            _: Option<i32>,
+            _: Option<i32>,
+            _: Option<&TidyCounts>,
+        ) -> i32 {
+            checks_failed
+        }
+
+        fn make_headers(&self, _: Option<bool>) -> HeaderMap<HeaderValue> {
+            HeaderMap::new()
+        }
+
+        fn get_list_of_changed_files(&self, _: &[&str], _: &PathSet, _: &PathSet) -> Vec<FileObj> {
+            Vec::new()
+        }
+
+        fn post_feedback(
+            &self,
+            _: &[FileObj],
+            _: &[FormatAdvice],
+            _: &[Vec<TidyNotification>],
+            _: &[ToolError],
+            _: &FeedbackInput,
+        ) -> i32 {
+            0
+        }
+    }
+
+    /// Create `count` files that each have a formatting concern and a clang-tidy
+    /// concern.
+    fn make_advice(count: usize) -> (Vec<FileObj>, Vec<FormatAdvice>, Vec<Vec<TidyNotification>>) {
+        let files = (0..count)
+            .map(|i| FileObj::new(PathBuf::from(format!("src/file_{i}.cpp"))))
+            .collect::<Vec<_>>();
+        let format_advice = (0..count)
+            .map(|_| FormatAdvice {
+                replacements: vec![Replacement {
+                    offset: 0,
+                    length: 1,
+                    value: None,
+                    line: Some(1),
+                    cols: Some(1),
+                }],
+                patch: None,
+            })
+            .collect();
+        let tidy_advice = (0..count)
+            .map(|i| {
+                vec![TidyNotification {
+                    filename: format!("src/file_{i}.cpp"),
+                    line: 1,
+                    cols: 1,
+                    severity: String::from("warning"),
+                    rationale: "a long explanation ".repeat(10),
+                    diagnostic: String::from("bugprone-demo"),
+                    suggestion: vec![String::from("int a;")],
+                    related: vec![],
+                }]
+            })
+            .collect();
+        (files, format_advice, tidy_advice)
+    }
+
+    #[test]
+    fn comment_not_truncated() {
+        let (files, format_advice, tidy_advice) = make_advice(3);
+        let (limited, full, format_failed, tidy_failed) = TestClient.make_comment(
+            &files,
+            &format_advice,
+            &tidy_advice,
+            &[],
+            &FeedbackInput::default(),
+            Some(65535),
+        );
+        assert_eq!(full, limited);
+        assert_eq!((format_failed, tidy_failed), (3, 3));
+        assert!(!full.contains("not shown"));
+    }
+
+    #[test]
+    fn comment_failure_policy() {
+        let (files, _, tidy_advice) = make_advice(2);
+        let user_inputs = FeedbackInput {
+            failure_policy: FailurePolicy::new("error", None, None).unwrap(),
+            ..Default::default()
+        };
+        let (comment, _, format_failed, tidy_failed) =
+            TestClient.make_comment(&files, &[], &tidy_advice, &[], &user_inputs, None);
+        // informational concerns are shown, but they are not failures
+        assert_eq!((format_failed, tidy_failed), (0, 0));
+        assert!(comment.contains("No problems need attention."));
+        assert!(!comment.contains("did not pass"));
+        assert!(comment
+            .contains("<strong>2 concern(s)</strong> (0 counted as failures)</summary>\n\nAll concerns per diagnostic (informational):\n\n| Diagnostic |"));
+
+        let user_inputs = FeedbackInput {
+            failure_policy: FailurePolicy::new("warning", Some("bugprone-*"), None).unwrap(),
+            ..Default::default()
+        };
+        let (comment, _, _, tidy_failed) =
+            TestClient.make_comment(&files, &[], &tidy_advice, &[], &user_inputs, None);
+        assert_eq!(tidy_failed, 2);
+        assert!(comment.contains("# Cpp-Linter Report :warning:\nSome files did not pass the configured checks!\n\n<details>"));
+    }
+
+    #[test]
+    fn comment_truncated() {
+        let (files, format_advice, tidy_advice) = make_advice(1000);
+        let (comment, full, format_failed, tidy_failed) = TestClient.make_comment(
+            &files,
+            &format_advice,
+            &tidy_advice,
+            &[],
+            &FeedbackInput::default(),
+            Some(65535),
+        );
+        assert!(full.len() > 65535);
+        assert!(comment.len() <= 65535);
+        // all concerns are still counted
+        assert_eq!((format_failed, tidy_failed), (1000, 1000));
+        assert!(comment.contains("<strong>1000 concern(s)</strong>"));
+        // all formatting concerns fit, but not all clang-tidy concerns
+        assert!(comment.contains("- src/file_999.cpp\n"));
+        let shown = comment.matches("[bugprone-demo]").count();
+        assert!(shown > 0 && shown < 1000);
+        assert!(comment.contains(&format!(
+            "... and {} more concern(s) not shown.",
+            1000 - shown
+        )));
+        assert!(comment.contains("This report was truncated."));
+        assert!(comment.ends_with(")"));
+    }
+
+    fn make_notes(file: &str, notes: &[(&str, &str)]) -> Vec<TidyNotification> {
+        notes
+            .iter()
+            .map(|(severity, diagnostic)| TidyNotification {
+                filename: file.to_string(),
+                line: 1,
+                cols: 1,
+                severity: severity.to_string(),
+                rationale: String::new(),
+                diagnostic: diagnostic.to_string(),
+                suggestion: vec![],
+                related: vec![],
+            })
+            .collect()
+    }
+
+    #[test]
+    fn failure_policy() {
+        let files = vec![FileObj::new(PathBuf::from("src/demo.cpp"))];
+        let mut notes = make_notes(
+            "src/demo.cpp",
+            &[
+                ("error", "clang-analyzer-core.NullDereference"),
+                ("warning", "bugprone-branch-clone"),
+                ("warning", "readability-braces-around-statements"),
+                ("note", "readability-braces-around-statements"),
+            ],
+        );
+        // concerns about other files (not shown in the report) are not counted
+        notes.extend(make_notes("src/other.h", &[("error", "bugprone-x")]));
+        let tidy_advice = vec![notes];
+
+        let counts = FailurePolicy::default().count(&files, &tidy_advice);
+        assert_eq!(
+            counts,
+            TidyCounts {
+                errors: 1,
+                warnings: 2,
+                notes: 1,
+                failed: 4
+            }
+        );
+
+        let policy = FailurePolicy::new("error", None, None).unwrap();
+        assert_eq!(policy.count(&files, &tidy_advice).failed, 1);
+
+        let policy = FailurePolicy::new("warning", Some("bugprone-*, cert-*"), None).unwrap();
+        assert!(policy.is_blocking("warning", "bugprone-branch-clone"));
+        assert!(!policy.is_blocking("note", "bugprone-branch-clone"));
+        assert!(!policy.is_blocking("error", "clang-analyzer-core.NullDereference"));
+        assert_eq!(policy.count(&files, &tidy_advice).failed, 1);
+
+        // 3 informational concerns exceed the budget
+        let policy = FailurePolicy::new("note", Some("bugprone-*"), Some(2)).unwrap();
+        assert_eq!(policy.count(&files, &tidy_advice).failed, 4);
+        let policy = FailurePolicy::new("note", Some("bugprone-*"), Some(3)).unwrap();
+        assert_eq!(policy.count(&files, &tidy_advice).failed, 1);
+
+        let err = FailurePolicy::new("note", Some("bugprone-[*"), None).unwrap_err();
+        assert!(err.starts_with("Invalid --blocking-checks pattern"));
+        let err = FailurePolicy::new("fatal", Some("bugprone-*"), None).unwrap_err();
+        assert!(err.starts_with("Invalid --fail-severity \"fatal\""));
+    }
 }
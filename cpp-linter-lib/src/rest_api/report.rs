@@ -0,0 +1,757 @@
+//! This module holds functionality related to rendering the report (used for thread
+//! comments, step summaries, and check run summaries) from a template.
+//!
+//! Templates use the [Handlebars](https://handlebarsjs.com/guide/) syntax. The data
+//! available to a template is described by [`ReportContext`]. The built-in
+//! [`DEFAULT_TEMPLATE`] is a good starting point for a custom template.
+
+use std::path::PathBuf;
+
+// non-std crates
+use handlebars::{no_escape, Handlebars};
+use serde::Serialize;
+
+// project specific modules/crates
+use crate::clang_tools::{
+    clang_format::FormatAdvice,
+    clang_tidy::{RelatedLocation, TidyNotification},
+    ToolError,
+};
+use crate::common_fs::FileObj;
+use crate::rest_api::{make_comment_marker, FeedbackInput};
+
+/// The built-in template used to render the report.
+pub const DEFAULT_TEMPLATE: &str = r#"{{marker}}
+# Cpp-Linter Report {{#if checks_failed}}:warning:
+Some files did not pass the configured checks!{{else}}:heavy_check_mark:
+No problems need attention.{{/if}}
+{{#if format_checks_failed}}
+
+<details><summary>clang-format reports: <strong>{{format_checks_failed}} file(s) not formatted</strong></summary>
+
+{{#each format_files}}
+{{#if patch}}
+<details><summary>{{name}}</summary>
+
+```diff
+{{patch}}
+```
+
+</details>
+
+{{else}}
+- {{name}}
+{{/if}}
+{{/each}}
+{{#if format_omitted}}
+
+... and {{format_omitted}} more concern(s) not shown.
+{{/if}}
+
+</details>
+{{/if}}
+{{#if tidy_concerns}}
+
+<details><summary>clang-tidy reports: <strong>{{tidy_concerns}} concern(s)</strong> ({{tidy_checks_failed}} counted as failures)</summary>
+
+All concerns per diagnostic (informational):
+
+| Diagnostic | Severity | Count |
+|------------|----------|------:|
+{{#each tidy_summary}}
+| {{diagnostic}} | {{severity}} | {{count}} |
+{{/each}}
+{{#each tidy_files}}
+
+<details><summary>{{name}} ({{count}} concern(s))</summary>
+
+{{#each notes}}
+- <strong>{{filename}}:{{line}}:{{cols}}:</strong> {{severity}}: [{{diagnostic}}]
+   > {{rationale}}
+{{#each related}}
+   - {{filename}}:{{line}}:{{cols}}: note: {{message}}
+{{/each}}
+{{#if suggestion}}
+
+   ```{{extension}}
+   {{suggestion}}
+   ```
+{{/if}}
+{{/each}}
+
+</details>
+{{/each}}
+{{#if tidy_omitted}}
+
+... and {{tidy_omitted}} more concern(s) not shown.
+{{/if}}
+
+</details>
+{{/if}}
+{{#if truncated}}
+
+:information_source: This report was truncated. {{#if full_report_url}}See the [full report]({{full_report_url}}).{{else}}See the workflow run's summary for the full report.{{/if}}
+{{/if}}
+{{#if tool_errors}}
+
+<details><summary>:boom: <strong>Some files could not be analyzed</strong></summary>
+
+{{#each tool_errors}}
+- <strong>{{filename}}</strong>: {{tool}} failed
+   ```
+   {{message}}
+   ```
+{{/each}}
+
+</details>
+{{/if}}
+
+Have any feedback or feature suggestions? [Share it here.](https://github.com/cpp-linter/cpp-linter-action/issues)"#;
+
+/// A file that is not formatted as made available to the report's template.
+#[derive(Debug, Clone, Serialize)]
+pub struct FormatFileContext {
+    /// The file's path and name (relative to the repository root).
+    pub name: String,
+
+    /// A unified diff (without context lines) of the changes that clang-format wants
+    /// to make (if available).
+    pub patch: Option<String>,
+}
+
+/// A clang-tidy notification as made available to the report's template.
+#[derive(Debug, Clone, Serialize)]
+pub struct TidyNoteContext {
+    /// The file's path and name (relative to the repository root).
+    pub filename: String,
+
+    /// The line number from which the notification originated.
+    pub line: u32,
+
+    /// The column offset on the line from which the notification originated.
+    pub cols: u32,
+
+    /// The severity (ie error/warning/note) of the notification.
+    pub severity: String,
+
+    /// The diagnostic name as used when configuring clang-tidy.
+    pub diagnostic: String,
+
+    /// A helpful message explaining why the notification exists.
+    pub rationale: String,
+
+    /// The lines of code (joined and indented for a Markdown list item) that point to
+    /// the notification's origin (if any).
+    pub suggestion: String,
+
+    /// The file's extension (useful for syntax highlighting code blocks).
+    pub extension: String,
+
+    /// The locations (and notes) related to the notification (if any).
+    pub related: Vec<RelatedLocation>,
+}
+
+/// The clang-tidy concerns about a single file as made available to the report's
+/// template.
+#[derive(Debug, Clone, Serialize)]
+pub struct TidyFileContext {
+    /// The file's path and name (relative to the repository root).
+    pub name: String,
+
+    /// The number of clang-tidy concerns about the file (including any omitted from a
+    /// truncated report).
+    pub count: usize,
+
+    /// The clang-tidy concerns about the file (as shown in the report).
+    pub notes: Vec<TidyNoteContext>,
+}
+
+/// The number of clang-tidy concerns with the same diagnostic name and severity.
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct TidySummaryRow {
+    /// The diagnostic name as used when configuring clang-tidy.
+    pub diagnostic: String,
+
+    /// The severity (ie error/warning/note) of the concerns.
+    pub severity: String,
+
+    /// The number of concerns (including any omitted from a truncated report).
+    pub count: usize,
+}
+
+/// A failure of a clang tool to analyze a file as made available to the report's
+/// template.
+#[derive(Debug, Clone, Serialize)]
+pub struct ToolErrorContext {
+    /// The name of the tool (ie `clang-tidy` or `clang-format`).
+    pub tool: String,
+
+    /// The file's path and name (relative to the repository root).
+    pub filename: String,
+
+    /// A description of the failure (joined and indented for a Markdown list item).
+    pub message: String,
+}
+
+/// The data made available to the report's template.
+#[derive(Debug, Clone, Serialize)]
+pub struct ReportContext {
+    /// The hidden marker that identifies the report in a thread comment.
+    pub marker: String,
+
+    /// The total number of concerns that count as failures (including the
+    /// [`ReportContext::tool_checks_failed`]).
+    pub checks_failed: usize,
+
+    /// The number of files that are not formatted.
+    pub format_checks_failed: usize,
+
+    /// The number of clang-tidy concerns that count as failures (according to the
+    /// [`FailurePolicy`](crate::rest_api::FailurePolicy)).
+    pub tidy_checks_failed: usize,
+
+    /// The number of all clang-tidy concerns (including informational ones).
+    pub tidy_concerns: usize,
+
+    /// The number of files that a clang tool failed to analyze (or `0` if these do not
+    /// count as failures).
+    pub tool_checks_failed: usize,
+
+    /// The files that a clang tool failed to analyze (these are never omitted from a
+    /// truncated report).
+    pub tool_errors: Vec<ToolErrorContext>,
+
+    /// The files that are not formatted (as shown in the report).
+    pub format_files: Vec<FormatFileContext>,
+
+    /// The number of files (not formatted) omitted from a truncated report.
+    pub format_omitted: usize,
+
+    /// The clang-tidy concerns (as shown in the report).
+    pub tidy_notes: Vec<TidyNoteContext>,
+
+    /// The number of clang-tidy concerns omitted from a truncated report.
+    pub tidy_omitted: usize,
+
+    /// The clang-tidy concerns (as shown in the report) grouped by file.
+    pub tidy_files: Vec<TidyFileContext>,
+
+    /// The counts of all clang-tidy concerns per diagnostic name and severity (sorted
+    /// by descending count).
+    pub tidy_summary: Vec<TidySummaryRow>,
+
+    /// Is the report truncated?
+    pub truncated: bool,
+
+    /// The URL of the full report (only used if the report is truncated).
+    pub full_report_url: Option<String>,
+}
+
+impl ReportContext {
+    /// Create the context about the concerns in `format_advice` and `tidy_advice` for
+    /// the given set of `files`.
+    ///
+    /// The `user_inputs` decide which clang-tidy concerns count as failures (see
+    /// [`FeedbackInput::failure_policy`]), whether the `tool_errors` count as failures,
+    /// and the report's marker (see [`make_comment_marker()`]).
+    pub fn new(
+        files: &[FileObj],
+        format_advice: &[FormatAdvice],
+        tidy_advice: &[Vec<TidyNotification>],
+        tool_errors: &[ToolError],
+        user_inputs: &FeedbackInput,
+        full_report_url: Option<String>,
+    ) -> Self {
+        let mut format_files = Vec::new();
+        for (index, fmt_advice) in format_advice.iter().enumerate() {
+            if !fmt_advice.replacements.is_empty() {
+                format_files.push(FormatFileContext {
+                    name: files[index].name.to_string_lossy().replace('\\', "/"),
+                    patch: fmt_advice.patch.clone(),
+                });
+            }
+        }
+
+        let mut tidy_notes = Vec::new();
+        for (index, tidy_notes_of_file) in tidy_advice.iter().enumerate() {
+            for tidy_note in tidy_notes_of_file {
+                let file_path = PathBuf::from(&tidy_note.filename);
+                if file_path == files[index].name {
+                    tidy_notes.push(TidyNoteContext {
+                        filename: tidy_note.filename.clone(),
+                        line: tidy_note.line,
+                        cols: tidy_note.cols,
+                        severity: tidy_note.severity.clone(),
+                        diagnostic: tidy_note.diagnostic.clone(),
+                        rationale: tidy_note.rationale.clone(),
+                        suggestion: tidy_note.suggestion.join("\n    "),
+                        related: tidy_note.related.clone(),
+                        extension: file_path
+                            .extension()
+                            .expect("file extension was not determined")
+                            .to_string_lossy()
+                            .to_string(),
+                    });
+                }
+            }
+        }
+        let tidy_checks_failed =
+            user_inputs.failure_policy.count(files, tidy_advice).failed as usize;
+        let tool_checks_failed = if user_inputs.fail_on_tool_errors {
+            tool_errors.len()
+        } else {
+            0
+        };
+        ReportContext {
+            marker: make_comment_marker(&user_inputs.comment_key),
+            checks_failed: format_files.len() + tidy_checks_failed + tool_checks_failed,
+            format_checks_failed: format_files.len(),
+            tidy_checks_failed,
+            tidy_concerns: tidy_notes.len(),
+            tool_checks_failed,
+            tool_errors: tool_errors
+                .iter()
+                .map(|error| ToolErrorContext {
+                    tool: error.tool.clone(),
+                    filename: error.filename.clone(),
+                    message: error.message.replace('\n', "\n   "),
+                })
+                .collect(),
+            format_files,
+            format_omitted: 0,
+            tidy_files: group_by_file(&tidy_notes),
+            tidy_summary: summarize(&tidy_notes),
+            tidy_notes,
+            tidy_omitted: 0,
+            truncated: false,
+            full_report_url,
+        }
+    }
+
+    /// Create a copy of this context that only shows the first `count` concerns.
+    ///
+    /// Formatting concerns are shown before clang-tidy concerns. The counts of concerns
+    /// are not changed.
+    pub fn truncate(&self, count: usize) -> Self {
+        let mut context = self.clone();
+        let format_shown = count.min(self.format_files.len());
+        let tidy_shown = (count - format_shown).min(self.tidy_notes.len());
+        context.format_files.truncate(format_shown);
+        context.tidy_notes.truncate(tidy_shown);
+        context.format_omitted = self.format_files.len() - format_shown;
+        context.tidy_omitted = self.tidy_notes.len() - tidy_shown;
+        context.truncated = context.format_omitted + context.tidy_omitted > 0;
+        context.tidy_files = group_by_file(&context.tidy_notes);
+        for file in &mut context.tidy_files {
+            // keep the count of all concerns about the file
+            if let Some(all) = self.tidy_files.iter().find(|all| all.name == file.name) {
+                file.count = all.count;
+            }
+        }
+        context
+    }
+}
+
+/// Group the given clang-tidy `notes` by file (in order of first appearance).
+fn group_by_file(notes: &[TidyNoteContext]) -> Vec<TidyFileContext> {
+    let mut files: Vec<TidyFileContext> = Vec::new();
+    for note in notes {
+        match files.iter_mut().find(|file| file.name == note.filename) {
+            Some(file) => {
+                file.count += 1;
+                file.notes.push(note.clone());
+            }
+            None => files.push(TidyFileContext {
+                name: note.filename.clone(),
+                count: 1,
+                notes: vec![note.clone()],
+            }),
+        }
+    }
+    files
+}
+
+/// Count the given clang-tidy `notes` per diagnostic name and severity.
+///
+/// The rows are sorted by descending count, then by diagnostic name and severity.
+fn summarize(notes: &[TidyNoteContext]) -> Vec<TidySummaryRow> {
+    let mut rows: Vec<TidySummaryRow> = Vec::new();
+    for note in notes {
+        match rows
+            .iter_mut()
+            .find(|row| row.diagnostic == note.diagnostic && row.severity == note.severity)
+        {
+            Some(row) => row.count += 1,
+            None => rows.push(TidySummaryRow {
+                diagnostic: note.diagnostic.clone(),
+                severity: note.severity.clone(),
+                count: 1,
+            }),
+        }
+    }
+    rows.sort_by(|a, b| {
+        b.count
+            .cmp(&a.count)
+            .then_with(|| a.diagnostic.cmp(&b.diagnostic))
+            .then_with(|| a.severity.cmp(&b.severity))
+    });
+    rows
+}
+
+/// Check that the given `template` can be compiled.
+pub fn validate_template(template: &str) -> Result<(), String> {
+    handlebars::Template::compile(template)
+        .map(|_| ())
+        .map_err(|e| e.to_string())
+}
+
+/// Render the given `template` with the given `context`.
+///
+/// HTML escaping is disabled because the report is Markdown.
+pub fn render_report(template: &str, context: &ReportContext) -> Result<String, String> {
+    let mut registry = Handlebars::new();
+    registry.register_escape_fn(no_escape);
+    registry
+        .render_template(template, context)
+        .map_err(|e| e.to_string())
+}
+
+/// Render the report with the given `template` and `context`.
+///
+/// If the `template` fails to render, then the [`DEFAULT_TEMPLATE`] is used instead.
+fn render_or_default(template: &str, context: &ReportContext) -> String {
+    render_report(template, context).unwrap_or_else(|e| {
+        log::error!("Failed to render the report template: {e}");
+        render_report(DEFAULT_TEMPLATE, context).expect("the default report template should render")
+    })
+}
+
+/// Render the report with the given `template` and `context`.
+///
+/// If a `max_len` is given, then the report is shrunk to fit within that many bytes
+/// (see [`fit_report()`]). If the `template` fails to render, then the
+/// [`DEFAULT_TEMPLATE`] is used instead.
+pub fn make_report(template: &str, context: &ReportContext, max_len: Option<usize>) -> String {
+    let report = render_or_default(template, context);
+    match max_len {
+        Some(max_len) => fit_report(&report, template, context, max_len),
+        None => report,
+    }
+}
+
+/// Shrink the given `report` (rendered with the `template` and `context`) to fit within
+/// `max_len` bytes.
+///
+/// Concerns are omitted from the end of the report until it fits. If the report still
+/// does not fit without any concerns, then only the first line of each tool error's
+/// message is shown. As a last resort, the report is cut off with a notice about it.
+pub fn fit_report(report: &str, template: &str, context: &ReportContext, max_len: usize) -> String {
+    if report.len() <= max_len {
+        return report.to_string();
+    }
+    // binary search for the most concerns that can be shown
+    let (mut low, mut high) = (0, context.format_files.len() + context.tidy_notes.len());
+    let mut best = None;
+    while low < high {
+        let mid = (low + high) / 2;
+        let truncated = render_or_default(template, &context.truncate(mid));
+        if truncated.len() <= max_len {
+            best = Some(truncated);
+            low = mid + 1;
+        } else {
+            high = mid;
+        }
+    }
+    if let Some(report) = best {
+        return report;
+    }
+    let mut context = context.truncate(0);
+    let report = render_or_default(template, &context);
+    if report.len() <= max_len {
+        return report;
+    }
+    for error in &mut context.tool_errors {
+        if let Some((first, _)) = error.message.split_once('\n') {
+            error.message = first.to_string();
+        }
+    }
+    context.truncated = true;
+    let report = render_or_default(template, &context);
+    if report.len() <= max_len {
+        return report;
+    }
+    log::warn!("The report does not fit within {max_len} bytes, even without any concerns");
+    let mut end = max_len.saturating_sub(CUT_NOTICE.len());
+    while !report.is_char_boundary(end) {
+        end -= 1;
+    }
+    let mut report = report[..end].to_string();
+    if report.len() + CUT_NOTICE.len() <= max_len {
+        report.push_str(CUT_NOTICE);
+    }
+    report
+}
+
+/// The notice appended to a report that had to be cut off (see [`fit_report()`]).
+const CUT_NOTICE: &str = "\n\n:warning: This report was cut off because it is too long.\n";
+
+#[cfg(test)]
+mod test {
+    use super::{
+        group_by_file, make_report, summarize, validate_template, FormatFileContext,
+        RelatedLocation, ReportContext, TidyNoteContext, TidySummaryRow, ToolErrorContext,
+    };
+
+    fn make_context(count: usize) -> ReportContext {
+        let tidy_notes = (0..count)
+            .map(|i| TidyNoteContext {
+                filename: format!("src/file_{i}.cpp"),
+                line: 1,
+                cols: 2,
+                severity: String::from("warning"),
+                diagnostic: String::from("bugprone-demo"),
+                rationale: String::from("some <concern> & rationale"),
+                suggestion: String::from("int a;"),
+                extension: String::from("cpp"),
+                related: vec![],
+            })
+            .collect::<Vec<_>>();
+        ReportContext {
+            marker: String::from("<!-- cpp linter action -->"),
+            checks_failed: count * 2,
+            format_checks_failed: count,
+            tidy_checks_failed: count,
+            tidy_concerns: count,
+            tool_checks_failed: 0,
+            tool_errors: vec![],
+            format_files: (0..count)
+                .map(|i| FormatFileContext {
+                    name: format!("src/file_{i}.cpp"),
+                    patch: None,
+                })
+                .collect(),
+            format_omitted: 0,
+            tidy_files: group_by_file(&tidy_notes),
+            tidy_summary: summarize(&tidy_notes),
+            tidy_notes,
+            tidy_omitted: 0,
+            truncated: false,
+            full_report_url: Some(String::from("https://example.com/run")),
+        }
+    }
+
+    #[test]
+    fn custom_template() {
+        let template =
+            "{{checks_failed}} concerns{{#each tidy_notes}}\n{{filename}}: {{rationale}}{{/each}}";
+        assert!(validate_template(template).is_ok());
+        let report = make_report(template, &make_context(2), None);
+        assert_eq!(
+            report,
+            "4 concerns\nsrc/file_0.cpp: some <concern> & rationale\nsrc/file_1.cpp: some <concern> & rationale"
+        );
+    }
+
+    #[test]
+    fn bad_template() {
+        assert!(validate_template("{{#if checks_failed}}").is_err());
+    }
+
+    #[test]
+    fn truncated_template() {
+        let template = "{{#each format_files}}{{name}}\n{{/each}}{{#each tidy_notes}}{{filename}}\n{{/each}}{{#if truncated}}{{format_omitted}}+{{tidy_omitted}} omitted; see {{full_report_url}}{{/if}}";
+        let context = make_context(10);
+        let full = make_report(template, &context, None);
+        assert!(!full.contains("omitted"));
+        let report = make_report(template, &context, Some(full.len() - 1));
+        assert!(report.len() < full.len());
+        // omitting 3 concerns makes room for the note about omitted concerns
+        assert!(report.ends_with("0+3 omitted; see https://example.com/run"));
+        let shortest = "10+10 omitted; see https://example.com/run";
+        let report = make_report(template, &context, Some(shortest.len()));
+        assert_eq!(report, shortest);
+    }
+
+    #[test]
+    fn cut_off_report() {
+        let template = "{{#each tool_errors}}{{filename}}: {{message}}\n{{/each}}{{#if truncated}}truncated{{/if}}";
+        let mut context = make_context(0);
+        context.tool_errors = (0..100)
+            .map(|i| ToolErrorContext {
+                tool: String::from("clang-tidy"),
+                filename: format!("src/file_{i}.cpp"),
+                message: String::from("clang-tidy could not process the file.\n   some details"),
+            })
+            .collect();
+        let full = make_report(template, &context, None);
+        assert!(full.contains("some details"));
+        // the details of tool errors are omitted first
+        let shortened = make_report(template, &context, Some(full.len() - 1));
+        assert!(!shortened.contains("some details"));
+        assert!(shortened.ends_with("could not process the file.\ntruncated"));
+        // then the report is cut off
+        let report = make_report(template, &context, Some(500));
+        assert!(report.len() <= 500);
+        assert!(report.ends_with(super::CUT_NOTICE));
+        assert!(make_report(template, &context, Some(10)).len() <= 10);
+    }
+
+    fn make_note(filename: &str, line: u32, severity: &str, diagnostic: &str) -> TidyNoteContext {
+        TidyNoteContext {
+            filename: filename.to_string(),
+            line,
+            cols: 1,
+            severity: severity.to_string(),
+            diagnostic: diagnostic.to_string(),
+            rationale: String::from("rationale"),
+            suggestion: String::new(),
+            extension: String::from("cpp"),
+            related: vec![],
+        }
+    }
+
+    #[test]
+    fn grouped_notes() {
+        let notes = vec![
+            make_note("src/a.cpp", 1, "warning", "readability-x"),
+            make_note("src/a.cpp", 2, "warning", "bugprone-y"),
+            make_note("src/b.cpp", 3, "error", "bugprone-y"),
+            make_note("src/a.cpp", 4, "warning", "bugprone-y"),
+        ];
+        let files = group_by_file(&notes);
+        assert_eq!(files.len(), 2);
+        assert_eq!(files[0].name, "src/a.cpp");
+        assert_eq!(files[0].count, 3);
+        assert_eq!(
+            files[0].notes.iter().map(|n| n.line).collect::<Vec<_>>(),
+            vec![1, 2, 4]
+        );
+        assert_eq!(files[1].name, "src/b.cpp");
+        assert_eq!(files[1].count, 1);
+
+        let row = |diagnostic: &str, severity: &str, count: usize| TidySummaryRow {
+            diagnostic: diagnostic.to_string(),
+            severity: severity.to_string(),
+            count,
+        };
+        assert_eq!(
+            summarize(&notes),
+            vec![
+                row("bugprone-y", "warning", 2),
+                row("bugprone-y", "error", 1),
+                row("readability-x", "warning", 1),
+            ]
+        );
+    }
+
+    #[test]
+    fn grouped_notes_truncated() {
+        let mut context = make_context(0);
+        context.tidy_notes = vec![
+            make_note("src/a.cpp", 1, "warning", "readability-x"),
+            make_note("src/a.cpp", 2, "warning", "readability-x"),
+            make_note("src/b.cpp", 3, "warning", "readability-x"),
+        ];
+        context.tidy_files = group_by_file(&context.tidy_notes);
+        context.tidy_summary = summarize(&context.tidy_notes);
+        let truncated = context.truncate(1);
+        assert_eq!(truncated.tidy_omitted, 2);
+        assert_eq!(truncated.tidy_files.len(), 1);
+        assert_eq!(truncated.tidy_files[0].notes.len(), 1);
+        // counts include omitted concerns
+        assert_eq!(truncated.tidy_files[0].count, 2);
+        assert_eq!(truncated.tidy_summary[0].count, 3);
+    }
+
+    #[test]
+    fn default_template_grouped() {
+        let mut context = make_context(0);
+        context.tidy_notes = vec![
+            make_note("src/a.cpp", 1, "warning", "readability-x"),
+            make_note("src/a.cpp", 2, "warning", "readability-x"),
+        ];
+        context.tidy_files = group_by_file(&context.tidy_notes);
+        context.tidy_summary = summarize(&context.tidy_notes);
+        context.tidy_checks_failed = 2;
+        context.tidy_concerns = 2;
+        context.checks_failed = 2;
+        let report = make_report(super::DEFAULT_TEMPLATE, &context, None);
+        assert_eq!(
+            report
+                .matches("<summary>src/a.cpp (2 concern(s))</summary>")
+                .count(),
+            1
+        );
+        assert!(report.contains("| readability-x | warning | 2 |"));
+        assert!(report.contains("- <strong>src/a.cpp:2:1:</strong> warning: [readability-x]"));
+    }
+
+    #[test]
+    fn default_template_patch() {
+        let mut context = make_context(2);
+        context.format_files[1].patch = Some(String::from("@@ -1 +1 @@\n-int  a;\n+int a;"));
+        context.tidy_notes.clear();
+        context.tidy_files.clear();
+        context.tidy_summary.clear();
+        context.tidy_checks_failed = 0;
+        context.tidy_concerns = 0;
+        context.checks_failed = 2;
+        let report = make_report(super::DEFAULT_TEMPLATE, &context, None);
+        assert!(report.contains("- src/file_0.cpp\n"));
+        assert!(report.contains(
+            "<details><summary>src/file_1.cpp</summary>\n\n\
+             ```diff\n@@ -1 +1 @@\n-int  a;\n+int a;\n```\n\n</details>"
+        ));
+    }
+
+    #[test]
+    fn default_template_tool_errors() {
+        let mut context = make_context(0);
+        let report = make_report(super::DEFAULT_TEMPLATE, &context, None);
+        assert!(!report.contains("could not be analyzed"));
+        context.tool_errors = vec![ToolErrorContext {
+            tool: String::from("clang-tidy"),
+            filename: String::from("src/a.cpp"),
+            message: String::from(
+                "clang-tidy reported compilation errors.\n   Found compiler error(s).",
+            ),
+        }];
+        // tool errors are reported even if they do not count as failures
+        let report = make_report(super::DEFAULT_TEMPLATE, &context, None);
+        assert!(report.contains("No problems need attention."));
+        assert!(report.contains(
+            "<summary>:boom: <strong>Some files could not be analyzed</strong></summary>\n\n\
+             - <strong>src/a.cpp</strong>: clang-tidy failed\n   ```\n   \
+             clang-tidy reported compilation errors.\n   Found compiler error(s).\n   ```\n\n</details>\n\n\
+             Have any feedback"
+        ));
+        context.tool_checks_failed = 1;
+        context.checks_failed = 1;
+        let report = make_report(super::DEFAULT_TEMPLATE, &context, None);
+        assert!(report.contains("Some files did not pass the configured checks!"));
+        assert!(report.contains("could not be analyzed"));
+    }
+
+    #[test]
+    fn default_template_related() {
+        let mut context = make_context(0);
+        let mut note = make_note("src/a.cpp", 3, "warning", "bugprone-x");
+        note.related = vec![RelatedLocation {
+            filename: String::from("src/a.hpp"),
+            line: 1,
+            cols: 6,
+            message: String::from("previous declaration is here"),
+        }];
+        context.tidy_notes = vec![note];
+        context.tidy_files = group_by_file(&context.tidy_notes);
+        context.tidy_summary = summarize(&context.tidy_notes);
+        context.tidy_checks_failed = 1;
+        context.tidy_concerns = 1;
+        context.checks_failed = 1;
+        let report = make_report(super::DEFAULT_TEMPLATE, &context, None);
+        assert!(report.contains(
+            "- <strong>src/a.cpp:3:1:</strong> warning: [bugprone-x]\n   > rationale\n   \
+             - src/a.hpp:1:6: note: previous declaration is here\n"
+        ));
+    }
+}
@@ -4,6 +4,8 @@
 //! [`main()`].
 
 use std::env;
+use std::fs;
+use std::io::{self, Read};
 use std::path::{Path, PathBuf};
 
 // non-std crates
@@ -12,12 +14,18 @@ use log::{set_max_level, LevelFilter};
 use openssl_probe;
 
 // project specific modules/crates
-use crate::clang_tools::capture_clang_tools_output;
-use crate::cli::{convert_extra_arg_val, get_arg_parser, parse_ignore};
-use crate::common_fs::{list_source_files, FileObj};
+use crate::baseline::Baseline;
+use crate::clang_tools::clang_tidy::CompilationDatabase;
+use crate::clang_tools::{capture_clang_tools_output, ClangParams};
+use crate::cli::{convert_extra_arg_val, get_arg_parser, parse_file_list, parse_ignore};
+use crate::common_fs::{is_source_or_ignored, list_source_files, FileObj};
+use crate::config::Config;
+use crate::git::{
+    get_diff_from_refs, get_diff_to_workdir, list_tracked_files, open_repo, parse_diff,
+};
 use crate::github_api::GithubApiClient;
 use crate::logger::{self, end_log_group, start_log_group};
-use crate::rest_api::RestApiClient;
+use crate::rest_api::{report::validate_template, FailurePolicy, FeedbackInput, RestApiClient};
 
 #[cfg(feature = "openssl-vendored")]
 fn probe_ssl_certs() {
@@ -39,8 +47,11 @@ fn probe_ssl_certs() {}
 ///
 /// This is done because of the way the python entry point is invoked. If [`std::env::args`]
 /// is used instead of python's `sys.argv`, then the list of strings includes the entry point
-/// alias ("path/to/cpp-linter.exe"). Thus, the parser in [`crate::cli`] will halt on an error
-/// because it is not configured to handle positional arguments.
+/// alias ("path/to/cpp-linter.exe"). Thus, the parser in [`crate::cli`] would mistake the
+/// alias for a positional `files` argument.
+///
+/// Returns the exit status according to the `--exit-code` option (or 2 if the options
+/// that decide which concerns count as failures are invalid).
 pub fn run_main(args: Vec<String>) -> i32 {
     probe_ssl_certs();
 
@@ -85,6 +96,22 @@ pub fn run_main(args: Vec<String>) -> i32 {
         .collect::<Vec<_>>();
     let (ignored, not_ignored) = parse_ignore(&ignore);
 
+    let fail_severity = args.get_one::<String>("fail-severity").unwrap();
+    let blocking_checks = args.get_one::<String>("blocking-checks");
+    let max_warnings = args.get_one::<u32>("max-warnings").copied();
+    let failure_policy = match FailurePolicy::new(
+        fail_severity,
+        blocking_checks.map(String::as_str),
+        max_warnings,
+    ) {
+        Ok(policy) => policy,
+        Err(e) => {
+            log::error!("{e}");
+            // the same exit status as other invalid CLI arguments
+            return 2;
+        }
+    };
+
     let lines_changed_only = match args
         .get_one::<String>("lines-changed-only")
         .unwrap()
@@ -96,14 +123,77 @@ pub fn run_main(args: Vec<String>) -> i32 {
         _ => unreachable!(),
     };
     let files_changed_only = args.get_flag("files-changed-only");
+    let diff_base = args.get_one::<String>("diff-base");
+    let diff_workdir = args.get_one::<String>("diff-workdir").unwrap().as_str();
+
+    // gather the explicitly specified files (if any)
+    let mut file_list = args
+        .get_many::<String>("files")
+        .map(|files| files.map(|f| f.to_string()).collect::<Vec<_>>())
+        .unwrap_or_default();
+    if let Some(files_from) = args.get_one::<String>("files-from") {
+        let content = if files_from == "-" {
+            let mut buf = String::new();
+            io::stdin()
+                .read_to_string(&mut buf)
+                .expect("Failed to read list of files from stdin");
+            buf
+        } else {
+            fs::read_to_string(files_from)
+                .unwrap_or_else(|e| panic!("Failed to read list of files from {files_from}: {e}"))
+        };
+        file_list.extend(content.lines().map(|line| line.to_string()));
+    }
 
     start_log_group(String::from("Get list of specified source files"));
-    let files: Vec<FileObj> = if lines_changed_only != 0 || files_changed_only {
+    let files: Vec<FileObj> = if !file_list.is_empty() {
+        // use the explicitly specified files according to ignore values.
+        parse_file_list(&file_list.iter().map(|f| f.as_str()).collect::<Vec<_>>())
+            .into_iter()
+            .filter(|file| is_source_or_ignored(&file.name, &extensions, &ignored, &not_ignored))
+            .collect()
+    } else if diff_workdir != "false" {
+        // get diff of the working directory from libgit2 API
+        let repo = open_repo(".")
+            .expect("Please ensure the repository is checked out before running cpp-linter.");
+        let diff = get_diff_to_workdir(&repo, diff_workdir == "index")
+            .unwrap_or_else(|e| panic!("Could not get diff of working directory: {}", e.message()));
+        parse_diff(&diff, &extensions, &ignored, &not_ignored)
+    } else if let Some(base) = diff_base {
+        // get diff from libgit2 API using the specified refs
+        let repo = open_repo(".")
+            .expect("Please ensure the repository is checked out before running cpp-linter.");
+        let head = args.get_one::<String>("diff-head");
+        let diff = get_diff_from_refs(&repo, base, head.map(|h| h.as_str()))
+            .unwrap_or_else(|e| panic!("Could not get diff from {base}: {}", e.message()));
+        parse_diff(&diff, &extensions, &ignored, &not_ignored)
+    } else if lines_changed_only != 0 || files_changed_only {
         // parse_diff(github_rest_api_payload)
         rest_api_client.get_list_of_changed_files(&extensions, &ignored, &not_ignored)
+    } else if args.get_flag("files-from-database") {
+        // use the translation units listed in the compilation database.
+        let db_json = database_path
+            .as_ref()
+            .and_then(|db_path| CompilationDatabase::from_path(db_path))
+            .expect("A valid --database is required to use --files-from-database.");
+        db_json.list_source_files(&extensions, &ignored, &not_ignored)
+    } else if args.get_flag("tracked-only") {
+        // use the files listed in the git index according to ignore values.
+        let repo = open_repo(".")
+            .expect("Please ensure the repository is checked out before running cpp-linter.");
+        list_tracked_files(&repo, &extensions, &ignored, &not_ignored)
     } else {
         // walk the folder and look for files with specified extensions according to ignore values.
-        list_source_files(&extensions, &ignored, &not_ignored, ".")
+        let repo = if args.get_flag("respect-gitignore") {
+            let repo = open_repo(".");
+            if repo.is_err() {
+                log::warn!("Could not open the git repository to read .gitignore rules");
+            }
+            repo.ok()
+        } else {
+            None
+        };
+        list_source_files(&extensions, &ignored, &not_ignored, ".", repo.as_ref())
     };
     log::info!("Giving attention to the following files:");
     for file in &files {
@@ -111,32 +201,107 @@ pub fn run_main(args: Vec<String>) -> i32 {
     }
     end_log_group();
 
+    let config_path = args.get_one::<String>("config").unwrap();
+    let config = Config::from_file(Path::new(config_path))
+        .unwrap_or_else(|e| panic!("Failed to parse configuration file {config_path}: {e}"));
+
     let style = args.get_one::<String>("style").unwrap();
-    let extra_args = convert_extra_arg_val(&args);
-    let (format_advice, tidy_advice) = capture_clang_tools_output(
+    let clang_params = ClangParams {
+        tidy_checks: args.get_one::<String>("tidy-checks").unwrap().to_string(),
+        style: style.to_string(),
+        lines_changed_only,
+        extra_args: convert_extra_arg_val(&args)
+            .map(|extras| extras.iter().map(|arg| arg.to_string()).collect()),
+        header_filter: args.get_one::<String>("header-filter").cloned(),
+    };
+    let (mut format_advice, mut tidy_advice, tool_errors) = capture_clang_tools_output(
         &files,
         args.get_one::<String>("version").unwrap(),
-        args.get_one::<String>("tidy-checks").unwrap(),
-        style,
-        lines_changed_only,
+        &clang_params,
         database_path,
-        extra_args,
+        &config,
     );
+    if let Some(baseline_path) = args.get_one::<String>("baseline-create") {
+        let baseline = Baseline::from_findings(&files, &format_advice, &tidy_advice);
+        baseline
+            .to_file(Path::new(baseline_path))
+            .unwrap_or_else(|e| panic!("Failed to write baseline file {baseline_path}: {e}"));
+        log::info!(
+            "Recorded {} concern(s) in baseline file {baseline_path}",
+            baseline.findings.len()
+        );
+    }
+    if let Some(baseline_path) = args.get_one::<String>("baseline") {
+        let baseline = Baseline::from_file(Path::new(baseline_path))
+            .unwrap_or_else(|e| panic!("Failed to read baseline file {baseline_path}: {e}"));
+        let suppressed = baseline.filter(&files, &mut format_advice, &mut tidy_advice);
+        log::info!("Suppressed {suppressed} concern(s) recorded in baseline file {baseline_path}");
+    }
+    let comment_template = args.get_one::<String>("comment-template").map(|path| {
+        let template = fs::read_to_string(path)
+            .unwrap_or_else(|e| panic!("Failed to read comment template {path}: {e}"));
+        if let Err(e) = validate_template(&template) {
+            panic!("Failed to parse comment template {path}: {e}");
+        }
+        template
+    });
     start_log_group(String::from("Posting feedback"));
-    let no_lgtm = args.get_flag("no-lgtm");
-    let step_summary = args.get_flag("step-summary");
-    let thread_comments = args.get_one::<String>("thread-comments").unwrap();
-    let file_annotations = args.get_flag("file-annotations");
-    rest_api_client.post_feedback(
+    let user_inputs = FeedbackInput {
+        thread_comments: args
+            .get_one::<String>("thread-comments")
+            .unwrap()
+            .to_string(),
+        no_lgtm: args.get_flag("no-lgtm"),
+        step_summary: args.get_flag("step-summary"),
+        file_annotations: args.get_flag("file-annotations"),
+        style: style.to_string(),
+        comment_key: args.get_one::<String>("comment-key").unwrap().to_string(),
+        check_run: args.get_flag("check-run"),
+        check_failure_threshold: *args.get_one::<u32>("check-failure-threshold").unwrap(),
+        comment_template,
+        failure_policy,
+        fail_on_tool_errors: args.get_flag("fail-on-tool-errors"),
+    };
+    let checks_failed = rest_api_client.post_feedback(
         &files,
         &format_advice,
         &tidy_advice,
-        thread_comments,
-        no_lgtm,
-        step_summary,
-        file_annotations,
-        style,
+        &tool_errors,
+        &user_inputs,
     );
     end_log_group();
-    0
+    get_exit_code(
+        args.get_one::<String>("exit-code").unwrap(),
+        *args.get_one::<u32>("exit-code-threshold").unwrap(),
+        checks_failed,
+    )
+}
+
+/// Get the exit status for the given number of `checks_failed` according to the
+/// `--exit-code` `policy` and its `threshold`.
+fn get_exit_code(policy: &str, threshold: u32, checks_failed: i32) -> i32 {
+    let failed = match policy {
+        "fail-on-any" => checks_failed > 0,
+        "fail-on-threshold" => checks_failed > 0 && checks_failed as u32 >= threshold,
+        _ => false,
+    };
+    if failed {
+        log::info!("Exiting with status 1 because of {checks_failed} failed check(s)");
+    }
+    failed as i32
+}
+
+#[cfg(test)]
+mod test {
+    use super::get_exit_code;
+
+    #[test]
+    fn exit_codes() {
+        assert_eq!(get_exit_code("always-zero", 1, 5), 0);
+        assert_eq!(get_exit_code("fail-on-any", 1, 0), 0);
+        assert_eq!(get_exit_code("fail-on-any", 1, 1), 1);
+        assert_eq!(get_exit_code("fail-on-threshold", 3, 2), 0);
+        assert_eq!(get_exit_code("fail-on-threshold", 3, 3), 1);
+        assert_eq!(get_exit_code("fail-on-threshold", 0, 0), 0);
+    }
 }